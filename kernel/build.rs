@@ -1,13 +1,47 @@
-use std::fs::{read_dir, File};
-use std::io::{Result, Write};
+use std::fs::{read_dir, read_to_string, File};
+use std::io::{ErrorKind, Result, Write};
 
 fn main() {
     println!("cargo:rerun-if-changed=../user/src/");
     println!("cargo:rerun-if-changed={}", TARGET_PATH);
+    println!("cargo:rerun-if-changed={}", MANIFEST_PATH);
+    println!("cargo:rerun-if-changed={}", POLICY_PATH);
+    println!("cargo:rerun-if-changed={}", PARTITIONS_PATH);
     insert_app_data().unwrap();
 }
 
 static TARGET_PATH: &str = "../user/target/riscv64gc-unknown-none-elf/release/";
+static MANIFEST_PATH: &str = "../user/services.manifest";
+static POLICY_PATH: &str = "../user/access.policy";
+static PARTITIONS_PATH: &str = "../user/partitions.schedule";
+
+// A table such as the service manifest without comments and blank lines, one
+// entry per line with its fields separated by single spaces. The kernel parses
+// it at boot.
+fn read_table(path: &str) -> Result<String> {
+    let text = match read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(String::new()),
+        Err(err) => return Err(err),
+    };
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        // the line ends up in a .string directive
+        assert!(
+            !line.contains('"') && !line.contains('\\'),
+            "invalid character in {}: {}",
+            path,
+            line
+        );
+        lines.push(fields.join(" "));
+    }
+    Ok(lines.join("\\n"))
+}
 
 fn insert_app_data() -> Result<()> {
     let mut f = File::create("src/link_app.S").unwrap();
@@ -48,6 +82,23 @@ _app_names:"#
         writeln!(f, r#"    .string "{}""#, app)?;
     }
 
+    writeln!(
+        f,
+        r#"
+    .global _service_manifest
+_service_manifest:
+    .string "{}"
+    .global _access_policy
+_access_policy:
+    .string "{}"
+    .global _partition_schedule
+_partition_schedule:
+    .string "{}""#,
+        read_table(MANIFEST_PATH)?,
+        read_table(POLICY_PATH)?,
+        read_table(PARTITIONS_PATH)?
+    )?;
+
     for (idx, app) in apps.iter().enumerate() {
         println!("app_{}: {}", idx, app);
         writeln!(
@@ -0,0 +1,24 @@
+// Error codes returned by syscalls, negated in the same way as Linux.
+// Older syscalls still return a bare -1 for any failure.
+
+/// No such service or object.
+pub const ENOENT: isize = -2;
+/// The handle is not valid in the handle table of the caller.
+pub const EBADF: isize = -9;
+/// Nothing to read, or no room to write, and the caller asked not to block.
+pub const EAGAIN: isize = -11;
+/// No free address range to map granted pages at.
+pub const ENOMEM: isize = -12;
+/// The handle lacks a right required by the operation.
+pub const EACCES: isize = -13;
+/// The name is already taken by another kind of object.
+pub const EEXIST: isize = -17;
+/// Malformed arguments, such as a handle listed twice.
+pub const EINVAL: isize = -22;
+/// The other endpoint of the channel is closed.
+pub const EPIPE: isize = -32;
+/// The user buffer is too small for the pending message, or the message
+/// is larger than the queue of the channel.
+pub const EMSGSIZE: isize = -90;
+/// No reply arrived before the deadline of a call.
+pub const ETIMEDOUT: isize = -110;
@@ -1,9 +1,17 @@
 mod stdio;
+use crate::ipc::Signals;
 use crate::mm::UserBuffer;
+use crate::task::WaitQueue;
 
 pub trait File: Send + Sync {
     fn read(&self, buf: UserBuffer) -> usize;
     fn write(&self, buf: UserBuffer) -> usize;
+    // Signals currently asserted, for object_wait_many
+    fn signals(&self) -> Signals;
+    // Woken up when the signals change, None if they have to be polled
+    fn wait_queue(&self) -> Option<&WaitQueue> {
+        None
+    }
 }
 
 pub use stdio::{Stdin, Stdout};
@@ -1,17 +1,28 @@
+use crate::ipc::Signals;
 use crate::task::suspend_current_and_run_next;
 use kernel_hal::sbi::console_getchar;
+use lazy_static::*;
+use spin::Mutex;
 use super::File;
 
 pub struct Stdin;
 
 pub struct Stdout;
 
+lazy_static! {
+    // a character fetched by Stdin::signals, the console cannot peek
+    static ref STDIN_PENDING: Mutex<Option<u8>> = Mutex::new(None);
+}
+
 impl File for Stdin {
     fn read(&self, mut buf: crate::mm::UserBuffer) -> usize {
         assert_eq!(buf.len(), 1);
         let mut c: usize;
         loop {
-            c = console_getchar();
+            c = match STDIN_PENDING.lock().take() {
+                Some(ch) => ch as usize,
+                None => console_getchar(),
+            };
             if c == 0 {
                 suspend_current_and_run_next();
                 continue;
@@ -29,6 +40,21 @@ impl File for Stdin {
     fn write(&self, _buf: crate::mm::UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
     }
+
+    fn signals(&self) -> Signals {
+        let mut pending = STDIN_PENDING.lock();
+        if pending.is_none() {
+            match console_getchar() {
+                0 => {}
+                c => *pending = Some(c as u8),
+            }
+        }
+        if pending.is_some() {
+            Signals::READABLE
+        } else {
+            Signals::empty()
+        }
+    }
 }
 
 impl File for Stdout {
@@ -42,4 +68,8 @@ impl File for Stdout {
         }
         buf.len()
     }
+
+    fn signals(&self) -> Signals {
+        Signals::WRITABLE
+    }
 }
@@ -1,30 +1,188 @@
-use alloc::{collections::VecDeque, sync::{Arc, Weak}, vec::Vec};
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use spin::Mutex;
 
-use crate::task::PidHandle;
+use kernel_hal::PAGE_SIZE;
+
+use super::{Handle, Signals};
+use crate::mm::MapArea;
+use crate::task::{current_task, TaskControlBlock, WaitQueue};
 
 type T = MessagePacket;
 
+// transaction ids of calls, 0 marks a plain message
+static NEXT_TXID: AtomicUsize = AtomicUsize::new(1);
+
 pub struct Channel {
     peer: Weak<Channel>,
-    recv_queue: Mutex<VecDeque<T>>,
+    limits: ChannelLimits,
+    recv_queue: Mutex<RecvQueue>,
+    // tasks blocked on an empty recv_queue, a full peer queue or waiting for a reply
+    wait_queue: WaitQueue,
+    // calls made through this endpoint, filled in when the reply arrives
+    calls: Mutex<BTreeMap<usize, Option<T>>>,
+    // requests read from this endpoint and not replied yet, with the task which
+    // read them and borrows the priority of the caller until the reply
+    outstanding: Mutex<BTreeMap<usize, Weak<TaskControlBlock>>>,
+    // the task which last read or waited to read from this endpoint
+    reader: Mutex<Weak<TaskControlBlock>>,
+    // requests queued at this endpoint, with the reader they lent their
+    // priority to until one reads them
+    queued_calls: Mutex<BTreeMap<usize, Weak<TaskControlBlock>>>,
+}
+
+/// Bounds on the messages queued at each endpoint, fixed when the channel is created.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ChannelLimits {
+    pub max_msgs: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for ChannelLimits {
+    fn default() -> Self {
+        Self {
+            max_msgs: 64,
+            max_bytes: 64 * 1024,
+        }
+    }
+}
+
+/// Occupancy of the receive queue of an endpoint, hwm_* are the high-water marks.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ChannelStats {
+    pub msgs: usize,
+    pub bytes: usize,
+    pub max_msgs: usize,
+    pub max_bytes: usize,
+    pub hwm_msgs: usize,
+    pub hwm_bytes: usize,
+    pub dropped: usize,
+}
+
+#[derive(Default)]
+struct RecvQueue {
+    msgs: VecDeque<T>,
+    // payload bytes of the queued messages
+    bytes: usize,
+    hwm_msgs: usize,
+    hwm_bytes: usize,
+    // messages discarded by publish_msg
+    dropped: usize,
 }
 
 #[repr(C)]
 pub struct MessagePacket {
-    pub data: Vec<u8>,
-    pub handle: PidHandle, // use pid as handle temporally
+    // shared by the copies a topic queues for its subscribers
+    pub data: Arc<[u8]>,
+    pub sender: usize, // pid of the sending task
+    // handles moved out of the sender, installed into the receiver on read
+    pub handles: Vec<Handle>,
+    pub txid: usize,
+    pub is_reply: bool,
+    // effective priority of the caller, lent to the server of a request
+    pub priority: usize,
+    // pages moved out of the sender, mapped into the receiver on read
+    pub grant: Option<MapArea>,
+}
+
+/// What a receiver learns about a message besides its payload.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct MessageInfo {
+    pub len: usize,
+    pub sender: usize,
+    pub handles: usize,
+    // nonzero if the message is a call, pass it to channel_reply
+    pub txid: usize,
+    // size of the pages granted with the message
+    pub grant_len: usize,
+    // where the granted pages were mapped, set once the message is read
+    pub grant_addr: usize,
+}
+
+pub enum RecvError {
+    // no message is queued
+    ShouldWait,
+    // the front message does not fit, it stays queued
+    BufferTooSmall(MessageInfo),
+    // no message is queued and the other endpoint is gone
+    PeerClosed,
+    // the reader has no free range to map the pages granted by the front message, it stays queued
+    NoMemory,
+}
+
+pub enum SendError {
+    // the other endpoint is gone, the message is dropped
+    PeerClosed,
+    // the transaction id does not name an unanswered request
+    BadTxid,
+    // the queue of the other endpoint is full
+    ShouldWait,
+    // the message exceeds max_bytes and would never fit
+    TooLarge,
+    // no room or no reply before the deadline
+    TimedOut,
+}
+
+impl MessagePacket {
+    pub fn info(&self) -> MessageInfo {
+        MessageInfo {
+            len: self.data.len(),
+            sender: self.sender,
+            handles: self.handles.len(),
+            txid: self.txid,
+            grant_len: self.grant.as_ref().map_or(0, |area| area.page_count() * PAGE_SIZE),
+            grant_addr: 0,
+        }
+    }
+}
+
+impl RecvQueue {
+    fn has_room(&self, len: usize, limits: &ChannelLimits) -> bool {
+        self.msgs.len() < limits.max_msgs && self.bytes + len <= limits.max_bytes
+    }
+
+    fn push(&mut self, msg: T) {
+        self.bytes += msg.data.len();
+        self.msgs.push_back(msg);
+        self.hwm_msgs = self.hwm_msgs.max(self.msgs.len());
+        self.hwm_bytes = self.hwm_bytes.max(self.bytes);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let msg = self.msgs.pop_front()?;
+        self.bytes -= msg.data.len();
+        Some(msg)
+    }
 }
 
 impl Channel {
-    pub fn create() -> (Arc<Self>, Arc<Self>) {
+    pub fn create(limits: ChannelLimits) -> (Arc<Self>, Arc<Self>) {
         let mut channel0 = Arc::new(Channel {
             peer: Weak::default(),
+            limits,
             recv_queue: Default::default(),
+            wait_queue: Default::default(),
+            calls: Default::default(),
+            outstanding: Default::default(),
+            reader: Default::default(),
+            queued_calls: Default::default(),
         });
         let channel1 = Arc::new(Channel {
             peer: Arc::downgrade(&channel0),
+            limits,
             recv_queue: Default::default(),
+            wait_queue: Default::default(),
+            calls: Default::default(),
+            outstanding: Default::default(),
+            reader: Default::default(),
+            queued_calls: Default::default(),
         });
         unsafe {
             Arc::get_mut_unchecked(&mut channel0).peer = Arc::downgrade(&channel1);
@@ -32,30 +190,295 @@ impl Channel {
         (channel0, channel1)
     }
 
-    // Read a packet from the channel
-    pub fn read_msg(&self) -> Option<MessagePacket> {
+    // Read a packet of at most max_len bytes and max_handles handles from the channel
+    pub fn read_msg(&self, max_len: usize, max_handles: usize) -> Result<MessagePacket, RecvError> {
+        let task = current_task().unwrap();
+        *self.reader.lock() = Arc::downgrade(&task);
         let mut recv_queue = self.recv_queue.lock();
-        if let Some(_msg) = recv_queue.front() {
-            let msg = recv_queue.pop_front().unwrap();
-            return Some(msg);
+        if let Some(msg) = recv_queue.msgs.front() {
+            if msg.data.len() > max_len || msg.handles.len() > max_handles {
+                return Err(RecvError::BufferTooSmall(msg.info()));
+            }
+            if let Some(area) = msg.grant.as_ref() {
+                if !task.acquire_inner_lock().has_free_area(area.page_count()) {
+                    return Err(RecvError::NoMemory);
+                }
+            }
+            let msg = recv_queue.pop().unwrap();
+            drop(recv_queue);
+            if msg.txid != 0 {
+                // the reader serves the caller until it replies, at the priority of
+                // the caller, which moves over from the task it was lent to
+                if let Some(lender) = self.queued_calls.lock().remove(&msg.txid) {
+                    return_priority(&lender, msg.txid);
+                }
+                task.acquire_inner_lock().lent_priority.insert(msg.txid, msg.priority);
+                self.outstanding.lock().insert(msg.txid, Arc::downgrade(&task));
+            }
+            // writers blocked on a full queue may go on
+            if let Some(peer) = self.peer.upgrade() {
+                peer.wait_queue.wake_all();
+            }
+            Ok(msg)
+        } else if self.peer_closed() {
+            Err(RecvError::PeerClosed)
         } else {
-            None
+            Err(RecvError::ShouldWait)
+        }
+    }
+
+    // Read a packet from the channel, blocking until one arrives unless nonblock is set
+    pub fn recv_msg(
+        &self,
+        max_len: usize,
+        max_handles: usize,
+        nonblock: bool,
+    ) -> Result<MessagePacket, RecvError> {
+        loop {
+            match self.read_msg(max_len, max_handles) {
+                Err(RecvError::ShouldWait) if !nonblock => {
+                    // a killed task gives up, it exits before reaching user space
+                    if !self.wait_queue.wait() {
+                        return Err(RecvError::ShouldWait);
+                    }
+                }
+                result => return result,
+            }
         }
     }
 
-    // Write a packet to the channel
-    pub fn write_msg(&self, msg: T) {
-        let peer = self.peer.upgrade().unwrap();
-        peer.push_general(msg);
+    // Whether other is the other endpoint of this channel
+    pub fn is_peer(&self, other: &Arc<Channel>) -> bool {
+        Weak::ptr_eq(&self.peer, &Arc::downgrade(other))
+    }
+
+    // Write a packet to the channel, failing with ShouldWait if the queue of the peer is full
+    pub fn write_msg(&self, msg: T) -> Result<(), SendError> {
+        self.write_msg_or_return(msg).map_err(|(err, _)| err)
     }
 
-    fn push_general(&self, msg: T) {
-        let mut send_queue = self.recv_queue.lock();
-        send_queue.push_back(msg);
+    // Like write_msg, but hand msg back on failure, so that the caller can give
+    // back what it took for the message, such as granted pages
+    pub fn write_msg_or_return(&self, msg: T) -> Result<(), (SendError, T)> {
+        match self.peer.upgrade() {
+            Some(peer) => peer.push_general(msg),
+            None => Err((SendError::PeerClosed, msg)),
+        }
     }
 
-    #[allow(dead_code)]
-    fn peer_closed(&self) -> bool {
+    // Wait until the queue of the peer has room for len more bytes, or deadline_ms passes.
+    // Nothing can fill the queue between this and write_msg as long as the caller does not block.
+    pub fn wait_writable(&self, len: usize, nonblock: bool, deadline_ms: usize) -> Result<(), SendError> {
+        if len > self.limits.max_bytes {
+            return Err(SendError::TooLarge);
+        }
+        loop {
+            let peer = self.peer.upgrade().ok_or(SendError::PeerClosed)?;
+            if peer.recv_queue.lock().has_room(len, &self.limits) {
+                return Ok(());
+            }
+            // a reference to the peer must not keep it open while we sleep
+            drop(peer);
+            if nonblock {
+                return Err(SendError::ShouldWait);
+            }
+            if !self.wait_queue.wait_until(deadline_ms) {
+                return Err(SendError::TimedOut);
+            }
+        }
+    }
+
+    // Send msg as a request and wait for the reply until deadline_ms.
+    // A reply arriving after a timeout is dropped.
+    pub fn call_msg(&self, mut msg: T, deadline_ms: usize) -> Result<T, SendError> {
+        self.wait_writable(msg.data.len(), false, deadline_ms)?;
+        let txid = NEXT_TXID.fetch_add(1, Ordering::Relaxed);
+        msg.txid = txid;
+        msg.is_reply = false;
+        let priority = current_task().unwrap().acquire_inner_lock().effective_priority();
+        msg.priority = priority;
+        self.calls.lock().insert(txid, None);
+        if let Err(err) = self.write_msg(msg) {
+            self.calls.lock().remove(&txid);
+            return Err(err);
+        }
+        // a server busy with something else is not starved while the request waits
+        if let Some(peer) = self.peer.upgrade() {
+            peer.lend_to_reader(txid, priority);
+        }
+        loop {
+            let mut calls = self.calls.lock();
+            if let Some(reply) = calls.get_mut(&txid).unwrap().take() {
+                calls.remove(&txid);
+                return Ok(reply);
+            }
+            // the server died before answering
+            if self.peer_closed() {
+                calls.remove(&txid);
+                return Err(SendError::PeerClosed);
+            }
+            drop(calls);
+            if !self.wait_queue.wait_until(deadline_ms) {
+                return self.calls.lock().remove(&txid).unwrap().ok_or(SendError::TimedOut);
+            }
+        }
+    }
+
+    // Lend priority to the reader of this endpoint until it reads request txid
+    fn lend_to_reader(&self, txid: usize, priority: usize) {
+        let reader = self.reader.lock().clone();
+        if let Some(task) = reader.upgrade() {
+            task.acquire_inner_lock().lent_priority.insert(txid, priority);
+            self.queued_calls.lock().insert(txid, reader);
+        }
+    }
+
+    // Keep the reply of a call which did not fit the buffers of the caller
+    pub fn keep_reply(&self, reply: T) {
+        self.calls.lock().insert(reply.txid, Some(reply));
+    }
+
+    // Take the reply kept for call txid, None if there is none. The reply stays
+    // kept if it does not fit max_len and max_handles.
+    pub fn take_reply(
+        &self,
+        txid: usize,
+        max_len: usize,
+        max_handles: usize,
+    ) -> Option<Result<T, MessageInfo>> {
+        let mut calls = self.calls.lock();
+        let reply = calls.get(&txid)?.as_ref()?;
+        if reply.data.len() > max_len || reply.handles.len() > max_handles {
+            return Some(Err(reply.info()));
+        }
+        calls.remove(&txid).unwrap().map(Ok)
+    }
+
+    // Answer the request txid read from this endpoint
+    pub fn reply_msg(&self, txid: usize, mut msg: T) -> Result<(), SendError> {
+        // replies bypass the queue but not its bound on the message size
+        if msg.data.len() > self.limits.max_bytes {
+            return Err(SendError::TooLarge);
+        }
+        let server = self.outstanding.lock().remove(&txid).ok_or(SendError::BadTxid)?;
+        return_priority(&server, txid);
+        msg.txid = txid;
+        msg.is_reply = true;
+        self.write_msg(msg)
+    }
+
+    // Write a packet without ever blocking: if the queue of the peer is full, either
+    // the oldest queued messages or msg itself are dropped, and counted in the stats
+    pub fn publish_msg(&self, msg: T, drop_oldest: bool) -> Result<(), SendError> {
+        let peer = self.peer.upgrade().ok_or(SendError::PeerClosed)?;
+        let mut recv_queue = peer.recv_queue.lock();
+        let too_large = msg.data.len() > self.limits.max_bytes;
+        if !recv_queue.has_room(msg.data.len(), &self.limits) && (too_large || !drop_oldest) {
+            recv_queue.dropped += 1;
+            return Err(SendError::ShouldWait);
+        }
+        while !recv_queue.has_room(msg.data.len(), &self.limits) {
+            recv_queue.pop();
+            recv_queue.dropped += 1;
+        }
+        recv_queue.push(msg);
+        drop(recv_queue);
+        peer.wait_queue.wake_all();
+        Ok(())
+    }
+
+    fn push_general(&self, msg: T) -> Result<(), (SendError, T)> {
+        if msg.is_reply {
+            // replies bypass the limits, each call has room for exactly one
+            let mut calls = self.calls.lock();
+            // nobody waits for the reply of a timed out call
+            if let Some(slot) = calls.get_mut(&msg.txid) {
+                *slot = Some(msg);
+            }
+        } else {
+            let mut recv_queue = self.recv_queue.lock();
+            if !recv_queue.has_room(msg.data.len(), &self.limits) {
+                return Err((SendError::ShouldWait, msg));
+            }
+            recv_queue.push(msg);
+        }
+        // readers and callers share the queue, let each of them recheck
+        self.wait_queue.wake_all();
+        Ok(())
+    }
+
+    // Largest message the queue of the peer can ever hold
+    pub fn max_bytes(&self) -> usize {
+        self.limits.max_bytes
+    }
+
+    pub fn peer_closed(&self) -> bool {
         self.peer.strong_count() == 0
     }
+
+    pub fn signals(&self) -> Signals {
+        let mut signals = Signals::empty();
+        if !self.recv_queue.lock().msgs.is_empty() {
+            signals |= Signals::READABLE;
+        }
+        match self.peer.upgrade() {
+            Some(peer) => {
+                if peer.recv_queue.lock().has_room(0, &self.limits) {
+                    signals |= Signals::WRITABLE;
+                }
+            }
+            None => signals |= Signals::PEER_CLOSED,
+        }
+        signals
+    }
+
+    pub fn stats(&self) -> ChannelStats {
+        let recv_queue = self.recv_queue.lock();
+        ChannelStats {
+            msgs: recv_queue.msgs.len(),
+            bytes: recv_queue.bytes,
+            max_msgs: self.limits.max_msgs,
+            max_bytes: self.limits.max_bytes,
+            hwm_msgs: recv_queue.hwm_msgs,
+            hwm_bytes: recv_queue.hwm_bytes,
+            dropped: recv_queue.dropped,
+        }
+    }
+
+    // Messages queued at the peer, which a write through this endpoint joins.
+    // usize::MAX once the peer is closed.
+    pub fn peer_queued(&self) -> usize {
+        match self.peer.upgrade() {
+            Some(peer) => peer.recv_queue.lock().msgs.len(),
+            None => usize::MAX,
+        }
+    }
+
+    // Tasks waiting for the signals of this endpoint to change
+    pub fn wait_queue(&self) -> &WaitQueue {
+        &self.wait_queue
+    }
+}
+
+// The request txid is answered or abandoned, the server stops running for the caller
+fn return_priority(server: &Weak<TaskControlBlock>, txid: usize) {
+    if let Some(task) = server.upgrade() {
+        task.acquire_inner_lock().lent_priority.remove(&txid);
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        // requests which will never be read or answered
+        for (txid, server) in self.outstanding.lock().iter() {
+            return_priority(server, *txid);
+        }
+        for (txid, reader) in self.queued_calls.lock().iter() {
+            return_priority(reader, *txid);
+        }
+        // readers and callers blocked on the other endpoint will see PeerClosed
+        if let Some(peer) = self.peer.upgrade() {
+            peer.wait_queue.wake_all();
+        }
+    }
 }
@@ -0,0 +1,116 @@
+use alloc::{string::String, sync::Arc};
+
+use super::{Channel, Timer, Topic};
+use crate::errno::{EACCES, EBADF};
+use crate::task::WaitQueue;
+
+bitflags! {
+    /// What the holder of a handle may do with the object behind it.
+    pub struct Rights: u32 {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const DUPLICATE = 1 << 2;
+        const TRANSFER = 1 << 3;
+    }
+}
+
+bitflags! {
+    /// Observable states of a kernel object.
+    pub struct Signals: u32 {
+        const READABLE = 1 << 0;
+        const WRITABLE = 1 << 1;
+        const PEER_CLOSED = 1 << 2;
+        const TIMER_FIRED = 1 << 3;
+    }
+}
+
+impl Rights {
+    pub fn channel_default() -> Self {
+        Rights::READ | Rights::WRITE | Rights::DUPLICATE | Rights::TRANSFER
+    }
+}
+
+#[derive(Clone)]
+pub enum KernelObject {
+    Channel(Arc<Channel>),
+    Timer(Arc<Timer>),
+    Topic(Arc<Topic>),
+}
+
+impl KernelObject {
+    pub fn signals(&self) -> Signals {
+        match self {
+            KernelObject::Channel(channel) => channel.signals(),
+            KernelObject::Timer(timer) => timer.signals(),
+            // publishing never blocks
+            KernelObject::Topic(_) => Signals::WRITABLE,
+        }
+    }
+
+    // Woken up whenever the signals of the object may have changed,
+    // None if they never change
+    pub fn wait_queue(&self) -> Option<&WaitQueue> {
+        match self {
+            KernelObject::Channel(channel) => Some(channel.wait_queue()),
+            KernelObject::Timer(timer) => Some(timer.wait_queue()),
+            KernelObject::Topic(_) => None,
+        }
+    }
+}
+
+/// An entry in the handle table of a task.
+#[derive(Clone)]
+pub struct Handle {
+    pub object: KernelObject,
+    pub rights: Rights,
+    // path of the service the handle was connected to, copies keep it
+    pub service: Option<Arc<String>>,
+}
+
+impl Handle {
+    pub fn new(object: KernelObject, rights: Rights) -> Self {
+        Self { object, rights, service: None }
+    }
+
+    // Sends through a handle to a service are subject to the access policy
+    pub fn for_service(mut self, path: String) -> Self {
+        self.service = Some(Arc::new(path));
+        self
+    }
+
+    pub fn check_rights(&self, rights: Rights) -> Result<(), isize> {
+        if self.rights.contains(rights) {
+            Ok(())
+        } else {
+            Err(EACCES)
+        }
+    }
+
+    // Get the channel behind this handle if it grants the given rights
+    pub fn channel(&self, rights: Rights) -> Result<Arc<Channel>, isize> {
+        self.check_rights(rights)?;
+        match &self.object {
+            KernelObject::Channel(channel) => Ok(channel.clone()),
+            // the handle refers to another kind of object
+            _ => Err(EBADF),
+        }
+    }
+
+    // Get the topic behind this handle if it grants the given rights
+    pub fn topic(&self, rights: Rights) -> Result<Arc<Topic>, isize> {
+        self.check_rights(rights)?;
+        match &self.object {
+            KernelObject::Topic(topic) => Ok(topic.clone()),
+            _ => Err(EBADF),
+        }
+    }
+
+    // Get the timer behind this handle if it grants the given rights
+    pub fn timer(&self, rights: Rights) -> Result<Arc<Timer>, isize> {
+        self.check_rights(rights)?;
+        match &self.object {
+            KernelObject::Timer(timer) => Ok(timer.clone()),
+            _ => Err(EBADF),
+        }
+    }
+}
@@ -1,3 +1,9 @@
 mod channel;
+mod handle;
+mod timer;
+mod topic;
 
-pub use channel::*;
\ No newline at end of file
+pub use channel::*;
+pub use handle::*;
+pub use timer::*;
+pub use topic::*;
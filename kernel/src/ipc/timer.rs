@@ -0,0 +1,85 @@
+use alloc::{boxed::Box, sync::Arc};
+use spin::Mutex;
+
+use super::Signals;
+use crate::task::WaitQueue;
+use crate::timer::{add_timer, cancel_timer, TimerId};
+
+/// A one-shot timer which tasks can wait on through a handle.
+pub struct Timer {
+    inner: Mutex<TimerInner>,
+    // tasks waiting for the timer to fire
+    wait_queue: WaitQueue,
+}
+
+struct TimerInner {
+    armed: Option<TimerId>,
+    // stays set until the timer is set again or cancelled
+    fired: bool,
+}
+
+impl Timer {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            inner: Mutex::new(TimerInner {
+                armed: None,
+                fired: false,
+            }),
+            wait_queue: WaitQueue::new(),
+        })
+    }
+
+    // Fire once get_time_ms() reaches deadline_ms, replacing an earlier deadline
+    pub fn set(self: &Arc<Self>, deadline_ms: usize) {
+        let mut inner = self.inner.lock();
+        if let Some(id) = inner.armed.take() {
+            cancel_timer(id);
+        }
+        inner.fired = false;
+        let timer = Arc::downgrade(self);
+        inner.armed = Some(add_timer(
+            deadline_ms,
+            Box::new(move || {
+                if let Some(timer) = timer.upgrade() {
+                    timer.fire();
+                }
+            }),
+        ));
+    }
+
+    pub fn cancel(&self) {
+        let mut inner = self.inner.lock();
+        if let Some(id) = inner.armed.take() {
+            cancel_timer(id);
+        }
+        inner.fired = false;
+    }
+
+    fn fire(&self) {
+        let mut inner = self.inner.lock();
+        inner.armed = None;
+        inner.fired = true;
+        drop(inner);
+        self.wait_queue.wake_all();
+    }
+
+    pub fn signals(&self) -> Signals {
+        if self.inner.lock().fired {
+            Signals::TIMER_FIRED
+        } else {
+            Signals::empty()
+        }
+    }
+
+    pub fn wait_queue(&self) -> &WaitQueue {
+        &self.wait_queue
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(id) = self.inner.lock().armed.take() {
+            cancel_timer(id);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use alloc::{sync::Arc, vec::Vec};
+use spin::Mutex;
+
+use super::{Channel, ChannelLimits, MessagePacket};
+
+/// What a subscriber loses when its queue is full.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    DropOldest,
+    DropNewest,
+}
+
+struct Subscriber {
+    // write end of the queue the subscriber reads from
+    endpoint: Arc<Channel>,
+    policy: OverflowPolicy,
+}
+
+/// A named fan-out point, every published message is queued for each subscriber.
+pub struct Topic {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl Topic {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            subscribers: Mutex::new(Vec::new()),
+        })
+    }
+
+    // Add a subscriber with its own queue and return the endpoint it reads from.
+    // Closing that endpoint, e.g. when the subscriber exits, unsubscribes it.
+    pub fn subscribe(&self, policy: OverflowPolicy, limits: ChannelLimits) -> Arc<Channel> {
+        let (reader, endpoint) = Channel::create(limits);
+        self.subscribers.lock().push(Subscriber { endpoint, policy });
+        reader
+    }
+
+    // Largest message any subscriber can queue, the default bound without subscribers
+    pub fn max_bytes(&self) -> usize {
+        let subscribers = self.subscribers.lock();
+        match subscribers.iter().map(|subscriber| subscriber.endpoint.max_bytes()).max() {
+            Some(max_bytes) => max_bytes,
+            None => ChannelLimits::default().max_bytes,
+        }
+    }
+
+    // Queue data for every subscriber and return how many got it
+    pub fn publish(&self, data: Arc<[u8]>, sender: usize) -> usize {
+        let mut subscribers = self.subscribers.lock();
+        subscribers.retain(|subscriber| !subscriber.endpoint.peer_closed());
+        let mut delivered = 0;
+        for subscriber in subscribers.iter() {
+            let msg = MessagePacket {
+                data: data.clone(),
+                sender,
+                handles: Vec::new(),
+                txid: 0,
+                is_reply: false,
+                priority: 0,
+                grant: None,
+            };
+            let drop_oldest = subscriber.policy == OverflowPolicy::DropOldest;
+            if subscriber.endpoint.publish_msg(msg, drop_oldest).is_ok() {
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+}
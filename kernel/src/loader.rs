@@ -57,6 +57,42 @@ pub fn get_app_data_by_name(name: &str) -> Option<&'static [u8]> {
         .map(|i| get_app_data(i))
 }
 
+// Read a NUL-terminated string embedded by build.rs
+fn embedded_str(start: *const u8) -> &'static str {
+    unsafe {
+        let mut end = start;
+        while end.read_volatile() != '\0' as u8 {
+            end = end.add(1);
+        }
+        let slice = slice::from_raw_parts(start, end as usize - start as usize);
+        core::str::from_utf8(slice).unwrap()
+    }
+}
+
+// The service manifest embedded by build.rs, one service per line
+pub fn get_service_manifest() -> &'static str {
+    extern "C" {
+        fn _service_manifest();
+    }
+    embedded_str(_service_manifest as usize as *const u8)
+}
+
+// The access policy embedded by build.rs, one rule per line
+pub fn get_access_policy() -> &'static str {
+    extern "C" {
+        fn _access_policy();
+    }
+    embedded_str(_access_policy as usize as *const u8)
+}
+
+// The partition schedule embedded by build.rs, one partition or window per line
+pub fn get_partition_schedule() -> &'static str {
+    extern "C" {
+        fn _partition_schedule();
+    }
+    embedded_str(_partition_schedule as usize as *const u8)
+}
+
 pub fn list_apps() {
     println!("/**** APPS ****");
     for app in APP_NAMES.iter() {
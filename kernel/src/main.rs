@@ -19,6 +19,7 @@ extern crate bitflags;
 
 #[macro_use]
 mod console;
+mod errno;
 mod mm;
 mod task;
 mod trap;
@@ -27,6 +28,7 @@ mod fs;
 mod ipc;
 mod service;
 mod syscall;
+mod timer;
 
 global_asm!(include_str!("entry.asm"));
 global_asm!(include_str!("link_app.S"));
@@ -46,7 +48,12 @@ pub fn rust_main(_hartid: usize, _dtb_pa: usize) -> ! {
     clear_bss();
     mm::init();
     mm::remap_test();
+    task::init_scheduler();
     task::add_initproc();
+    service::load_access_policy();
+    #[cfg(feature = "user-nameserver")]
+    service::start_nameserver();
+    service::start_services();
     trap::init();
     trap::enable_timer_interrupt();
     kernel_hal::timer::set_next_trigger();
@@ -110,6 +110,30 @@ impl MapArea {
         }
     }
 
+    pub fn page_count(&self) -> usize {
+        self.vpn_range.get_end().0 - self.vpn_range.get_start().0
+    }
+
+    // Move the area to start at start_vpn, the frames go along without being copied
+    fn relocate(&mut self, start_vpn: VirtPageNum) {
+        let old_start = self.vpn_range.get_start();
+        let end_vpn = VirtPageNum(start_vpn.0 + self.page_count());
+        self.vpn_range = VPNRange::new(start_vpn, end_vpn);
+        let data_frames = core::mem::take(&mut self.data_frames);
+        self.data_frames = data_frames
+            .into_iter()
+            .map(|(vpn, frame)| (VirtPageNum(vpn.0 - old_start.0 + start_vpn.0), frame))
+            .collect();
+    }
+
+    // Map the frames the area already owns
+    fn map_existing(&self, page_table: &mut PageTable) {
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        for (vpn, frame) in self.data_frames.iter() {
+            page_table.map(*vpn, frame.ppn, pte_flags);
+        }
+    }
+
     pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) {
         assert_eq!(self.map_type, MapType::Framed);
         let mut current_vpn = self.vpn_range.get_start();
@@ -220,6 +244,38 @@ impl MemorySet {
         self.areas.remove(index);
     }
 
+    // Detach the framed area [start_va, end_va) from this address space, keeping its frames
+    pub fn take_framed_area(&mut self, start_va: VirtAddr, end_va: VirtAddr) -> Option<MapArea> {
+        let vpn_start = start_va.floor();
+        let vpn_end = end_va.ceil();
+        // the trap context is framed too, but never user accessible
+        let index = self.areas.iter().position(|area| {
+            area.map_type == MapType::Framed
+                && area.map_perm.contains(MapPermission::U)
+                && area.vpn_range.get_start() == vpn_start
+                && area.vpn_range.get_end() == vpn_end
+        })?;
+        let area = self.areas.remove(index);
+        for vpn in area.vpn_range {
+            self.page_table.unmap(vpn);
+        }
+        Some(area)
+    }
+
+    // Map an area taken by take_framed_area back where it was
+    pub fn restore_framed_area(&mut self, area: MapArea) {
+        area.map_existing(&mut self.page_table);
+        self.areas.push(area);
+    }
+
+    // Map an area taken from another address space at start_vpn and return its address
+    pub fn insert_moved_area(&mut self, mut area: MapArea, start_vpn: VirtPageNum) -> VirtAddr {
+        area.relocate(start_vpn);
+        area.map_existing(&mut self.page_table);
+        self.areas.push(area);
+        start_vpn.into()
+    }
+
     pub fn find_free_areas(&mut self, page_num: usize) -> Option<VirtPageNum> {
         self.areas.sort();
         if self.areas[0].vpn_range.get_start().0 - 0 > page_num {
@@ -10,7 +10,7 @@ pub fn init() {
 }
 
 pub use memory_set::remap_test;
-pub use memory_set::{MapPermission, MemorySet, KERNEL_SPACE};
+pub use memory_set::{MapArea, MapPermission, MemorySet, KERNEL_SPACE};
 pub use page_table::{
     check_address_valid, translated_byte_buffer, translated_refmut, translated_str, UserBuffer,
     UserBufferIterator,
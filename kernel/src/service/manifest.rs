@@ -0,0 +1,150 @@
+use alloc::{vec, vec::Vec};
+use crate::loader::{get_app_data_by_name, get_service_manifest};
+use crate::task::MAX_PRIORITY;
+use super::{spawn, RestartMode, RestartPolicy, Service, REGISTRY};
+
+/// A service started at boot, one line of the manifest:
+/// `name binary priority restart deps`.
+struct ManifestEntry {
+    name: &'static str,
+    binary: &'static str,
+    priority: usize,
+    restart: RestartPolicy,
+    deps: Vec<&'static str>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    New,
+    // on the path of the current search, meeting it again closes a cycle
+    Visiting,
+    Started,
+    Skipped,
+}
+
+// never, on-failure:<max_retries>:<backoff_ms> or always:<max_retries>:<backoff_ms>
+fn parse_restart(field: &str) -> Option<RestartPolicy> {
+    let mut parts = field.split(':');
+    let mode = match parts.next()? {
+        "never" => return Some(RestartPolicy::default()),
+        "on-failure" => RestartMode::OnFailure,
+        "always" => RestartMode::Always,
+        _ => return None,
+    };
+    let max_retries = parts.next()?.parse().ok()?;
+    let backoff_ms = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(RestartPolicy { mode, max_retries, backoff_ms })
+}
+
+fn parse_line(line: &'static str) -> Option<ManifestEntry> {
+    let fields: Vec<&'static str> = line.split(' ').collect();
+    if fields.len() != 5 || Service::new(fields[0].into()).segments().is_none() {
+        return None;
+    }
+    let deps = match fields[4] {
+        "-" => Vec::new(),
+        deps => deps.split(',').collect(),
+    };
+    Some(ManifestEntry {
+        name: fields[0],
+        binary: fields[1],
+        priority: fields[2].parse().ok().filter(|priority| (1..=MAX_PRIORITY).contains(priority))?,
+        restart: parse_restart(fields[3])?,
+        deps,
+    })
+}
+
+fn parse(manifest: &'static str) -> Vec<ManifestEntry> {
+    let mut entries: Vec<ManifestEntry> = Vec::new();
+    for line in manifest.lines() {
+        match parse_line(line) {
+            Some(entry) if entries.iter().any(|other| other.name == entry.name) => {
+                println!("[kernel] service {} is listed twice, skipped", entry.name);
+            }
+            Some(entry) => entries.push(entry),
+            None => println!("[kernel] malformed service manifest line: {}", line),
+        }
+    }
+    entries
+}
+
+// Depth-first search which appends index to order after its dependencies.
+// Return whether the service can be started.
+fn visit(
+    entries: &[ManifestEntry],
+    states: &mut [VisitState],
+    order: &mut Vec<usize>,
+    index: usize,
+) -> bool {
+    let entry = &entries[index];
+    match states[index] {
+        VisitState::Started => return true,
+        VisitState::Skipped => return false,
+        VisitState::Visiting => {
+            println!("[kernel] dependency cycle through service {}", entry.name);
+            return false;
+        }
+        VisitState::New => {}
+    }
+    states[index] = VisitState::Visiting;
+    let mut ok = true;
+    if get_app_data_by_name(entry.binary).is_none() {
+        println!("[kernel] service {}: binary {} not found", entry.name, entry.binary);
+        ok = false;
+    }
+    for dep in entry.deps.iter() {
+        match entries.iter().position(|other| other.name == *dep) {
+            Some(dep_index) => {
+                if !visit(entries, states, order, dep_index) {
+                    println!(
+                        "[kernel] service {}: dependency {} cannot be started",
+                        entry.name, dep
+                    );
+                    ok = false;
+                }
+            }
+            None => {
+                println!("[kernel] service {}: unknown dependency {}", entry.name, dep);
+                ok = false;
+            }
+        }
+    }
+    if ok {
+        states[index] = VisitState::Started;
+        order.push(index);
+    } else {
+        states[index] = VisitState::Skipped;
+    }
+    ok
+}
+
+/// Start the services of the manifest as children of initproc, each one after
+/// the services it depends on. Services in a cycle, with a missing binary or
+/// depending on a service which cannot be started are reported and left out.
+pub fn start_services() {
+    let entries = parse(get_service_manifest());
+    let mut states = vec![VisitState::New; entries.len()];
+    let mut order = Vec::new();
+    for index in 0..entries.len() {
+        visit(&entries, &mut states, &mut order, index);
+    }
+    for index in order {
+        let entry = &entries[index];
+        let service = Service::new(entry.name.into());
+        match spawn(entry.binary, entry.priority) {
+            Some(pid) => {
+                // the names of the manifest are well-formed and distinct
+                REGISTRY.register(pid, &service).unwrap();
+                REGISTRY.set_restart(&service, entry.binary.into(), entry.restart, entry.priority);
+                REGISTRY.set_from_manifest(&service);
+                #[cfg(feature = "user-nameserver")]
+                super::forward_register(pid, entry.name);
+                println!("[kernel] started service {} as pid {}", entry.name, pid);
+            }
+            None => println!("[kernel] service {}: failed to start {}", entry.name, entry.binary),
+        }
+    }
+}
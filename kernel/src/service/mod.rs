@@ -1,36 +1,410 @@
+mod manifest;
+#[cfg(feature = "user-nameserver")]
+mod nameserver;
+mod policy;
+mod pool;
 mod service;
+mod watchdog;
 
-use alloc::string::String;
+use alloc::{boxed::Box, string::String, sync::{Arc, Weak}, vec::Vec};
 use hashbrown::HashMap;
+use kernel_hal::timer::get_time_ms;
 use spin::Mutex;
-use crate::task::PidHandle;
+use crate::errno::{EACCES, EEXIST, EINVAL, ENOENT};
+use crate::ipc::{Channel, Topic};
+use crate::loader::get_app_data_by_name;
+use crate::task::{add_task, find_task, set_task_priority, WaitQueue, INITPROC};
+use crate::timer::{add_timer, cancel_timer};
 use lazy_static::*;
 
-pub use service::Service;
+pub use manifest::start_services;
+#[cfg(feature = "user-nameserver")]
+pub use nameserver::{
+    forward_register, label_registration, nameserver_lookup, nameserver_register, start_nameserver,
+};
+pub use policy::{load_access_policy, may_send};
+pub use pool::Dispatch;
+pub use service::{RestartMode, RestartPolicy, Service};
+pub use watchdog::WatchdogAction;
 
+/// Services form a tree keyed on the dotted segments of their path,
+/// "com.test.sensor" is the child "sensor" of "com.test".
 pub struct Registry {
-    pub table: Mutex<HashMap<String, usize>> // Service.path, PidHandle
+    // has the empty path, the first segment of every name is one of its children
+    root: Arc<Service>,
+    // topics share the namespace of the services
+    topics: Mutex<HashMap<String, Arc<Topic>>>,
+    // tasks waiting for a service to be registered
+    wait_queue: WaitQueue,
 }
 
 impl Registry {
     pub fn new() -> Self {
         Self {
-            table: Mutex::new(HashMap::new()),
+            root: Arc::new(Service::new(String::new())),
+            topics: Mutex::new(HashMap::new()),
+            wait_queue: WaitQueue::new(),
         }
     }
 
-    pub fn register(&self, pid: &PidHandle, service: &Service) {
-        self.table.lock().insert(service.path.clone(), pid.0);
+    // Find the node named by service.path, the empty path is the root
+    pub fn find(&self, service: &Service) -> Option<Arc<Service>> {
+        if service.path.is_empty() {
+            return Some(self.root.clone());
+        }
+        let mut node = self.root.clone();
+        for segment in service.segments()? {
+            node = node.find_child(segment)?;
+        }
+        Some(node)
+    }
+
+    // Get the node named by segments, creating the namespace nodes on the way
+    fn find_or_create(&self, segments: Vec<&str>) -> Arc<Service> {
+        let mut node = self.root.clone();
+        for segment in segments {
+            node = match node.find_child(segment) {
+                Some(child) => child,
+                None => {
+                    let child = Service::new_child(&node, segment);
+                    node.add_child(child.clone());
+                    child
+                }
+            };
+        }
+        node
+    }
+
+    // Fail with EEXIST if a topic, a pool or a live task holds service.path
+    pub fn check_free(&self, service: &Service) -> Result<(), isize> {
+        if self.topics.lock().contains_key(&service.path) {
+            return Err(EEXIST);
+        }
+        if let Some(node) = self.find(service) {
+            let inner = node.acquire_inner_lock();
+            let owned = inner.pid.map_or(false, |owner| find_task(owner).is_some());
+            if owned || inner.pool.is_some() {
+                return Err(EEXIST);
+            }
+        }
+        Ok(())
+    }
+
+    // Bind the new task pid to service.path, creating the namespace nodes on the way.
+    // Fail with EINVAL if the path has an empty segment, or as check_free.
+    pub fn register(&self, pid: usize, service: &Service) -> Result<(), isize> {
+        let segments = service.segments().ok_or(EINVAL)?;
+        self.check_free(service)?;
+        let node = self.find_or_create(segments);
+        {
+            let mut inner = node.acquire_inner_lock();
+            inner.pid = Some(pid);
+            inner.endpoint = None;
+            inner.from_manifest = false;
+        }
+        self.wait_queue.wake_all();
+        Ok(())
+    }
+
+    // Bind the running task pid to service.path, clients connect to endpoint
+    // or to its mailbox. The task takes over the name unless another live task
+    // holds it, then EEXIST is returned, as for the name of a topic.
+    pub fn publish(
+        &self,
+        pid: usize,
+        service: &Service,
+        endpoint: Option<Arc<Channel>>,
+    ) -> Result<(), isize> {
+        let segments = service.segments().ok_or(EINVAL)?;
+        if self.topics.lock().contains_key(&service.path) {
+            return Err(EEXIST);
+        }
+        let node = self.find_or_create(segments);
+        {
+            let mut inner = node.acquire_inner_lock();
+            if let Some(owner) = inner.pid {
+                if owner != pid && find_task(owner).is_some() {
+                    return Err(EEXIST);
+                }
+            }
+            if inner.pool.is_some() {
+                return Err(EEXIST);
+            }
+            inner.pid = Some(pid);
+            inner.endpoint = endpoint;
+            inner.from_manifest = false;
+            // a published name is not restarted, the task did not come from it
+            inner.binary = None;
+            inner.restart = RestartPolicy::default();
+            inner.restarts = 0;
+        }
+        self.wait_queue.wake_all();
+        Ok(())
+    }
+
+    // Drop the binding of service.path, which must belong to pid
+    pub fn unpublish(&self, pid: usize, service: &Service) -> Result<(), isize> {
+        let node = self.find(service).ok_or(ENOENT)?;
+        {
+            let mut inner = node.acquire_inner_lock();
+            match inner.pid {
+                Some(owner) if owner == pid => {}
+                Some(_) => return Err(EACCES),
+                None => return Err(ENOENT),
+            }
+            inner.pid = None;
+            inner.endpoint = None;
+            inner.binary = None;
+            if let Some(watchdog) = inner.watchdog.take() {
+                cancel_timer(watchdog.timer);
+            }
+        }
+        self.prune(node);
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn remove(&self, service: &Service) {
-        self.table.lock().remove(&service.path);
+    // The channel to hand out to a client of service.path
+    pub fn connect(&self, service: &Service) -> Option<Arc<Channel>> {
+        let node = self.find(service)?;
+        let inner = node.acquire_inner_lock();
+        if let Some(endpoint) = inner.endpoint.as_ref() {
+            return Some(endpoint.clone());
+        }
+        if let Some(pool) = inner.pool.as_ref() {
+            return pool.first_endpoint();
+        }
+        let task = find_task(inner.pid?)?;
+        let mailbox = task.acquire_inner_lock().mailbox.clone();
+        Some(mailbox)
     }
 
-    pub fn find_task(&self, service: &Service) -> usize {
-        *self.table.lock().get(&service.path).unwrap()
+    // Wait until service.path is registered or deadline_ms passes and return its task
+    pub fn wait_for(&self, service: &Service, deadline_ms: usize) -> Option<usize> {
+        loop {
+            if let Some(pid) = self.find_task(service) {
+                return Some(pid);
+            }
+            if !self.wait_queue.wait_until(deadline_ms) {
+                return self.find_task(service);
+            }
+        }
     }
+
+    // Remember the app behind service.path so that it can be started again
+    // according to restart, which also resets the restart count
+    pub fn set_restart(
+        &self,
+        service: &Service,
+        binary: String,
+        restart: RestartPolicy,
+        priority: usize,
+    ) {
+        if let Some(node) = self.find(service) {
+            let mut inner = node.acquire_inner_lock();
+            inner.binary = Some(binary);
+            inner.restart = restart;
+            inner.restarts = 0;
+            inner.priority = priority;
+        }
+    }
+
+    // Mark the task registered under service.path as started from the manifest
+    pub fn set_from_manifest(&self, service: &Service) {
+        if let Some(node) = self.find(service) {
+            node.acquire_inner_lock().from_manifest = true;
+        }
+    }
+
+    // Called when task pid exits: unbind the names it was registered under
+    // and either drop them or schedule a restart after the backoff
+    pub fn task_exited(&self, pid: usize, exit_code: i32) {
+        for node in self.find_bound(pid) {
+            let mut inner = node.acquire_inner_lock();
+            if let Some(pool) = inner.pool.as_mut() {
+                // instances of a pool are not restarted
+                if pool.remove(pid) {
+                    if pool.is_empty() {
+                        inner.pool = None;
+                        drop(inner);
+                        self.prune(node);
+                    }
+                    continue;
+                }
+            }
+            inner.pid = None;
+            inner.endpoint = None;
+            if let Some(watchdog) = inner.watchdog.take() {
+                cancel_timer(watchdog.timer);
+            }
+            if inner.binary.is_some() && inner.restart.should_restart(exit_code, inner.restarts) {
+                let delay = inner.restart.backoff(inner.restarts);
+                inner.restarts += 1;
+                drop(inner);
+                // the node may be removed while the timer is pending
+                let service = Arc::downgrade(&node);
+                add_timer(
+                    get_time_ms().saturating_add(delay),
+                    Box::new(move || {
+                        if let Some(service) = service.upgrade() {
+                            REGISTRY.restart(service);
+                        }
+                    }),
+                );
+            } else {
+                drop(inner);
+                self.prune(node);
+            }
+        }
+    }
+
+    // Start the app of node again unless a task or a pool took the name meanwhile
+    fn restart(&self, node: Arc<Service>) {
+        let mut inner = node.acquire_inner_lock();
+        if inner.pid.is_some() || inner.pool.is_some() {
+            return;
+        }
+        let priority = inner.priority;
+        let pid = inner.binary.as_ref().and_then(|binary| spawn(binary, priority));
+        match pid {
+            Some(pid) => {
+                println!("[kernel] restarted service {} as pid {}", node.path, pid);
+                inner.pid = Some(pid);
+                drop(inner);
+                #[cfg(feature = "user-nameserver")]
+                forward_register(pid, &node.path);
+                self.wait_queue.wake_all();
+            }
+            None => {
+                drop(inner);
+                self.prune(node);
+            }
+        }
+    }
+
+    // Paths task pid was started under from the manifest, restarts included
+    pub fn manifest_names_of(&self, pid: usize) -> Vec<String> {
+        self.find_bound(pid)
+            .iter()
+            .filter(|node| {
+                let inner = node.acquire_inner_lock();
+                inner.pid == Some(pid) && inner.from_manifest
+            })
+            .map(|node| node.path.clone())
+            .collect()
+    }
+
+    // Nodes registered to task pid
+    fn find_bound(&self, pid: usize) -> Vec<Arc<Service>> {
+        let mut nodes = Vec::new();
+        let mut stack = alloc::vec![self.root.clone()];
+        while let Some(node) = stack.pop() {
+            let inner = node.acquire_inner_lock();
+            stack.extend(inner.child.iter().cloned());
+            if inner.serves(pid) {
+                drop(inner);
+                nodes.push(node);
+            }
+        }
+        nodes
+    }
+
+    // Remove the node named by service.path together with everything below it.
+    // Every service in the subtree must be bound to pid or to no live task.
+    pub fn remove(&self, pid: usize, service: &Service) -> Result<(), isize> {
+        let node = self.find(service).ok_or(ENOENT)?;
+        let parent = node.parent.as_ref().and_then(Weak::upgrade).ok_or(EINVAL)?;
+        let mut subtree = Vec::new();
+        let mut stack = alloc::vec![node.clone()];
+        while let Some(node) = stack.pop() {
+            let inner = node.acquire_inner_lock();
+            if let Some(owner) = inner.pid {
+                if owner != pid && find_task(owner).is_some() {
+                    return Err(EACCES);
+                }
+            }
+            if let Some(pool) = inner.pool.as_ref() {
+                if !pool.served_only_by(pid) {
+                    return Err(EACCES);
+                }
+            }
+            stack.extend(inner.child.iter().cloned());
+            drop(inner);
+            subtree.push(node);
+        }
+        // pending restarts find the node gone, the watchdogs are stopped here
+        for node in subtree {
+            if let Some(watchdog) = node.acquire_inner_lock().watchdog.take() {
+                cancel_timer(watchdog.timer);
+            }
+        }
+        parent.remove_child(&node);
+        self.prune(parent);
+        Ok(())
+    }
+
+    // Drop the namespace nodes left without any service below them
+    fn prune(&self, mut node: Arc<Service>) {
+        while let Some(parent) = node.parent.as_ref().and_then(Weak::upgrade) {
+            {
+                let inner = node.acquire_inner_lock();
+                if inner.is_bound() || !inner.child.is_empty() {
+                    return;
+                }
+            }
+            parent.remove_child(&node);
+            node = parent;
+        }
+    }
+
+    pub fn find_task(&self, service: &Service) -> Option<usize> {
+        let node = self.find(service)?;
+        let inner = node.acquire_inner_lock();
+        inner.pid.or_else(|| inner.pool.as_ref()?.first_pid())
+    }
+
+    // Paths of the nodes directly below service.path
+    pub fn list_children(&self, service: &Service) -> Option<Vec<String>> {
+        let node = self.find(service)?;
+        let inner = node.acquire_inner_lock();
+        Some(inner.child.iter().map(|child| child.path.clone()).collect())
+    }
+
+    // Paths of the registered services at or below service.path
+    pub fn find_prefix(&self, service: &Service) -> Option<Vec<String>> {
+        let mut paths = Vec::new();
+        let mut stack = alloc::vec![self.find(service)?];
+        while let Some(node) = stack.pop() {
+            let inner = node.acquire_inner_lock();
+            if inner.is_bound() {
+                paths.push(node.path.clone());
+            }
+            // reversed so that children come out in registration order
+            stack.extend(inner.child.iter().rev().cloned());
+        }
+        Some(paths)
+    }
+
+    // Get the topic named service.path, creating it on first use.
+    // Return None if a service is registered under that name.
+    pub fn open_topic(&self, service: &Service) -> Option<Arc<Topic>> {
+        if self.find_task(service).is_some() {
+            return None;
+        }
+        let mut topics = self.topics.lock();
+        let topic = topics
+            .entry(service.path.clone())
+            .or_insert_with(Topic::new);
+        Some(topic.clone())
+    }
+}
+
+// Start binary as a child of initproc, which reaps it once it exits
+fn spawn(binary: &str, priority: usize) -> Option<usize> {
+    let task = INITPROC.create(binary, get_app_data_by_name(binary)?);
+    set_task_priority(&task, priority);
+    let pid = task.getpid();
+    add_task(task);
+    Some(pid)
 }
 
 lazy_static! {
@@ -0,0 +1,153 @@
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use spin::Mutex;
+use crate::ipc::{Channel, ChannelLimits, Handle, KernelObject, MessagePacket, Rights};
+use crate::errno::{EINVAL, ENOENT, EPIPE};
+use crate::loader::get_app_data_by_name;
+use crate::task::{add_task, current_task, find_task, INITPROC};
+use crate::timer::deadline_after;
+use lazy_static::*;
+
+/// Handle of the name server channel in initproc and in the name server itself.
+pub const BOOTSTRAP_HANDLE: usize = 1;
+
+// First byte of a request to the name server, the name follows.
+// Kept in sync with user_lib::nameserver.
+const NS_REGISTER: u8 = 1;
+const NS_LOOKUP: u8 = 2;
+
+lazy_static! {
+    // write endpoint kept by the kernel to serve the name syscalls through
+    static ref NAMESERVER: Mutex<Option<Arc<Channel>>> = Mutex::new(None);
+}
+
+/// Start the nameserver app as a child of initproc and connect initproc to it
+/// through BOOTSTRAP_HANDLE, which tasks forked from initproc inherit.
+pub fn start_nameserver() {
+    let data = match get_app_data_by_name("nameserver") {
+        Some(data) => data,
+        None => {
+            println!("[kernel] nameserver not found, names stay in the kernel registry");
+            return;
+        }
+    };
+    let (server_end, client_end) = Channel::create(ChannelLimits::default());
+    let task = INITPROC.create("nameserver", data);
+    let server = task
+        .acquire_inner_lock()
+        .alloc_handle(Handle::new(KernelObject::Channel(server_end), Rights::READ));
+    let client = INITPROC.acquire_inner_lock().alloc_handle(Handle::new(
+        KernelObject::Channel(client_end.clone()),
+        Rights::WRITE | Rights::DUPLICATE | Rights::TRANSFER,
+    ));
+    assert!(server == BOOTSTRAP_HANDLE && client == BOOTSTRAP_HANDLE);
+    println!("[kernel] started nameserver as pid {}", task.getpid());
+    add_task(task);
+    *NAMESERVER.lock() = Some(client_end);
+}
+
+// A request to the name server from task sender, op followed by path
+fn request(op: u8, path: &str, sender: usize, handles: Vec<Handle>) -> MessagePacket {
+    let mut data: Vec<u8> = vec![op];
+    data.extend_from_slice(path.as_bytes());
+    MessagePacket {
+        data: data.into(),
+        sender,
+        handles,
+        txid: 0,
+        is_reply: false,
+        priority: 0,
+        grant: None,
+    }
+}
+
+// The request registering the mailbox of task pid as path, None if it is gone
+fn register_request(pid: usize, path: &str) -> Option<MessagePacket> {
+    let mailbox = find_task(pid)?.acquire_inner_lock().mailbox.clone();
+    // labelled like the handles of service_connect, so that sends through
+    // the duplicates the name server hands out obey the access policy and pools
+    let handle = Handle::new(
+        KernelObject::Channel(mailbox),
+        Rights::WRITE | Rights::DUPLICATE | Rights::TRANSFER,
+    )
+    .for_service(String::from(path));
+    Some(request(NS_REGISTER, path, pid, vec![handle]))
+}
+
+// Send request to the name server and block the current task until it answers,
+// return the status of the reply and the handle it carries
+fn call(nameserver: &Channel, request: MessagePacket) -> (isize, Option<Handle>) {
+    let mut reply = match nameserver.call_msg(request, deadline_after(usize::MAX)) {
+        Ok(reply) => reply,
+        // the name server is gone, or the caller was killed
+        Err(_) => return (EPIPE, None),
+    };
+    if reply.data.len() != 8 {
+        return (EINVAL, None);
+    }
+    let mut status = [0u8; 8];
+    status.copy_from_slice(&reply.data);
+    (isize::from_le_bytes(status), reply.handles.pop())
+}
+
+/// Tell the name server that task pid serves path, as if pid had registered
+/// its own mailbox, without waiting for the answer. Used where nothing may
+/// block, by the manifest at boot and by restarts.
+pub fn forward_register(pid: usize, path: &str) {
+    let nameserver = match NAMESERVER.lock().clone() {
+        Some(nameserver) => nameserver,
+        None => return,
+    };
+    let msg = match register_request(pid, path) {
+        Some(msg) => msg,
+        None => return,
+    };
+    if nameserver.write_msg(msg).is_err() {
+        println!("[kernel] failed to forward {} to the nameserver", path);
+    }
+}
+
+/// Register task pid as serving path with the name server and wait for its
+/// answer, 0 or an error such as EEXIST. None if there is no name server and
+/// names stay in the kernel registry.
+pub fn nameserver_register(pid: usize, path: &str) -> Option<isize> {
+    let nameserver = NAMESERVER.lock().clone()?;
+    match register_request(pid, path) {
+        Some(msg) => Some(call(&nameserver, msg).0),
+        None => Some(ENOENT),
+    }
+}
+
+/// Look path up through the name server for the current task: the pid of the
+/// task serving it and a handle to it, or an error such as ENOENT. None if
+/// there is no name server and names stay in the kernel registry.
+pub fn nameserver_lookup(path: &str) -> Option<Result<(usize, Handle), isize>> {
+    let nameserver = NAMESERVER.lock().clone()?;
+    let pid = current_task().unwrap().getpid();
+    let result = match call(&nameserver, request(NS_LOOKUP, path, pid, Vec::new())) {
+        (status, Some(handle)) if status >= 0 => Ok((status as usize, handle)),
+        (status, _) if status < 0 => Err(status),
+        _ => Err(EINVAL),
+    };
+    Some(result)
+}
+
+/// Label the handle of a registration sent through channel, if it leads to the
+/// name server, with the name registered. Sends through the copies handed out
+/// by lookups then obey the access policy and pools like service_connect's.
+pub fn label_registration(channel: &Arc<Channel>, data: &[u8], handles: &mut [Handle]) {
+    let to_nameserver = match NAMESERVER.lock().as_ref() {
+        Some(nameserver) => Arc::ptr_eq(nameserver, channel),
+        None => false,
+    };
+    if !to_nameserver || data.first() != Some(&NS_REGISTER) {
+        return;
+    }
+    // the name server ignores trailing NULs, so does the label
+    let name = match core::str::from_utf8(&data[1..]) {
+        Ok(name) => name.trim_end_matches('\0'),
+        Err(_) => return,
+    };
+    for handle in handles.iter_mut() {
+        handle.service = Some(Arc::new(String::from(name)));
+    }
+}
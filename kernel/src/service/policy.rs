@@ -0,0 +1,102 @@
+use alloc::{string::String, vec::Vec};
+use crate::loader::get_access_policy;
+use super::REGISTRY;
+use lazy_static::*;
+
+/// Who a rule lets send.
+enum Client {
+    Any,
+    // tasks running the app
+    App(&'static str),
+    // tasks the manifest started under a service matching the pattern. Names
+    // a task publishes or registers do not count, it could pick any of them.
+    Service(&'static str),
+}
+
+/// `allow <client> <service>`, client is `*`, `app:<binary>` or `svc:<pattern>`.
+struct Rule {
+    client: Client,
+    service: &'static str,
+}
+
+/// Which clients may send to which services. A service no rule names is open
+/// to every task, one named by some rule only to the clients of its rules.
+pub struct AccessPolicy {
+    rules: Vec<Rule>,
+}
+
+// A pattern is a path, a prefix followed by ".*" or "*" for every path
+fn matches(pattern: &str, path: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_suffix(".*") {
+        Some(prefix) => path.len() > prefix.len() + 1
+            && path.starts_with(prefix)
+            && path.as_bytes()[prefix.len()] == b'.',
+        None => pattern == path,
+    }
+}
+
+fn parse_rule(line: &'static str) -> Option<Rule> {
+    let fields: Vec<&'static str> = line.split(' ').collect();
+    if fields.len() != 3 || fields[0] != "allow" {
+        return None;
+    }
+    let client = if fields[1] == "*" {
+        Client::Any
+    } else if let Some(app) = fields[1].strip_prefix("app:") {
+        Client::App(app)
+    } else if let Some(pattern) = fields[1].strip_prefix("svc:") {
+        Client::Service(pattern)
+    } else {
+        return None;
+    };
+    Some(Rule { client, service: fields[2] })
+}
+
+impl AccessPolicy {
+    fn parse(policy: &'static str) -> Self {
+        let mut rules = Vec::new();
+        for line in policy.lines() {
+            match parse_rule(line) {
+                Some(rule) => rules.push(rule),
+                None => println!("[kernel] malformed access policy line: {}", line),
+            }
+        }
+        Self { rules }
+    }
+
+    // Whether a task running app and started as services may send to path
+    pub fn allows(&self, app: &str, services: &[String], path: &str) -> bool {
+        let mut guarded = false;
+        for rule in self.rules.iter().filter(|rule| matches(rule.service, path)) {
+            guarded = true;
+            let allowed = match rule.client {
+                Client::Any => true,
+                Client::App(name) => name == app,
+                Client::Service(pattern) => {
+                    services.iter().any(|service| matches(pattern, service))
+                }
+            };
+            if allowed {
+                return true;
+            }
+        }
+        !guarded
+    }
+}
+
+lazy_static! {
+    pub static ref ACCESS_POLICY: AccessPolicy = AccessPolicy::parse(get_access_policy());
+}
+
+/// Whether task pid running app may send to the service at path.
+pub fn may_send(pid: usize, app: &str, path: &str) -> bool {
+    ACCESS_POLICY.allows(app, &REGISTRY.manifest_names_of(pid), path)
+}
+
+/// Parse the access policy embedded by build.rs, reporting malformed rules.
+pub fn load_access_policy() {
+    lazy_static::initialize(&ACCESS_POLICY);
+}
@@ -0,0 +1,134 @@
+use alloc::{sync::Arc, vec::Vec};
+use crate::errno::{EEXIST, EINVAL, ENOENT};
+use crate::ipc::Channel;
+use crate::task::find_task;
+use super::{Registry, Service};
+
+/// How the messages sent to a pool are spread over its instances.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dispatch {
+    RoundRobin,
+    // the instance with the fewest messages waiting in its mailbox
+    LeastQueued,
+}
+
+struct Instance {
+    pid: usize,
+    // write endpoint of the mailbox of the instance
+    endpoint: Arc<Channel>,
+}
+
+/// Tasks serving one service path together.
+pub struct Pool {
+    dispatch: Dispatch,
+    instances: Vec<Instance>,
+    // instance to pick next for round-robin
+    next: usize,
+}
+
+impl Pool {
+    pub fn contains(&self, pid: usize) -> bool {
+        self.instances.iter().any(|instance| instance.pid == pid)
+    }
+
+    pub fn served_only_by(&self, pid: usize) -> bool {
+        self.instances.iter().all(|instance| instance.pid == pid)
+    }
+
+    pub fn first_pid(&self) -> Option<usize> {
+        self.instances.first().map(|instance| instance.pid)
+    }
+
+    pub fn first_endpoint(&self) -> Option<Arc<Channel>> {
+        self.instances.first().map(|instance| instance.endpoint.clone())
+    }
+
+    // Remove instance pid, return false if it is not in the pool
+    pub fn remove(&mut self, pid: usize) -> bool {
+        let len = self.instances.len();
+        self.instances.retain(|instance| instance.pid != pid);
+        self.instances.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    // The endpoint the next message goes to
+    fn pick(&mut self) -> Option<Arc<Channel>> {
+        if self.instances.is_empty() {
+            return None;
+        }
+        let index = match self.dispatch {
+            Dispatch::RoundRobin => {
+                let index = self.next % self.instances.len();
+                self.next = index + 1;
+                index
+            }
+            Dispatch::LeastQueued => (0..self.instances.len())
+                .min_by_key(|index| self.instances[*index].endpoint.peer_queued())
+                .unwrap(),
+        };
+        Some(self.instances[index].endpoint.clone())
+    }
+}
+
+impl Registry {
+    // Add task pid to the pool serving service.path, which is created with dispatch
+    // if needed. Fail with EEXIST if a single task is registered under the name.
+    pub fn join(&self, pid: usize, service: &Service, dispatch: Dispatch) -> Result<(), isize> {
+        let segments = service.segments().ok_or(EINVAL)?;
+        if self.topics.lock().contains_key(&service.path) {
+            return Err(EEXIST);
+        }
+        let endpoint = find_task(pid).ok_or(ENOENT)?.acquire_inner_lock().mailbox.clone();
+        let node = self.find_or_create(segments);
+        {
+            let mut inner = node.acquire_inner_lock();
+            if let Some(owner) = inner.pid {
+                if find_task(owner).is_some() {
+                    return Err(EEXIST);
+                }
+                inner.pid = None;
+            }
+            // the first instance decides the dispatch
+            let pool = inner.pool.get_or_insert_with(|| Pool {
+                dispatch,
+                instances: Vec::new(),
+                next: 0,
+            });
+            if pool.contains(pid) {
+                return Ok(());
+            }
+            pool.instances.push(Instance { pid, endpoint });
+        }
+        self.wait_queue.wake_all();
+        Ok(())
+    }
+
+    // Remove task pid from the pool serving service.path, the pool goes away
+    // with its last instance
+    pub fn leave(&self, pid: usize, service: &Service) -> Result<(), isize> {
+        let node = self.find(service).ok_or(ENOENT)?;
+        {
+            let mut inner = node.acquire_inner_lock();
+            let pool = inner.pool.as_mut().ok_or(ENOENT)?;
+            if !pool.remove(pid) {
+                return Err(ENOENT);
+            }
+            if !pool.is_empty() {
+                return Ok(());
+            }
+            inner.pool = None;
+        }
+        self.prune(node);
+        Ok(())
+    }
+
+    // The instance a message sent to path goes to, None unless a pool serves path
+    pub fn dispatch(&self, path: &str) -> Option<Arc<Channel>> {
+        let node = self.find(&Service::new(path.into()))?;
+        let mut inner = node.acquire_inner_lock();
+        inner.pool.as_mut()?.pick()
+    }
+}
@@ -1,9 +1,90 @@
 use alloc::{string::String, sync::{Arc, Weak}, vec::Vec};
+use spin::{Mutex, MutexGuard};
+use crate::ipc::Channel;
+use crate::task::DEFAULT_PRIORITY;
+use super::pool::Pool;
+use super::watchdog::Watchdog;
 
+/// A node of the service namespace, named by its full dotted path.
 pub struct Service {
     pub path: String,
     pub parent: Option<Weak<Service>>,
+    inner: Mutex<ServiceInner>,
+}
+
+pub struct ServiceInner {
     pub child: Vec<Arc<Service>>,
+    // task registered under this name, None for a pure namespace node
+    pub pid: Option<usize>,
+    // channel clients connect to, the mailbox of the task if None
+    pub endpoint: Option<Arc<Channel>>,
+    // app the service was started from, needed to start it again
+    pub binary: Option<String>,
+    pub restart: RestartPolicy,
+    // restarts done so far, bounded by restart.max_retries
+    pub restarts: usize,
+    // priority the app is started with
+    pub priority: usize,
+    pub watchdog: Option<Watchdog>,
+    // instances serving the name together, instead of pid
+    pub pool: Option<Pool>,
+    // pid was started under this name from the service manifest, unlike a name
+    // a task publishes or registers, which it chooses for itself
+    pub from_manifest: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RestartMode {
+    Never,
+    // only when the task exits with a non-zero code
+    OnFailure,
+    Always,
+}
+
+/// What to do when the task behind a service exits.
+#[derive(Clone, Copy)]
+pub struct RestartPolicy {
+    pub mode: RestartMode,
+    pub max_retries: usize,
+    // delay before the first restart, doubled for every further one
+    pub backoff_ms: usize,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            mode: RestartMode::Never,
+            max_retries: 0,
+            backoff_ms: 0,
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn should_restart(&self, exit_code: i32, restarts: usize) -> bool {
+        let wanted = match self.mode {
+            RestartMode::Never => false,
+            RestartMode::OnFailure => exit_code != 0,
+            RestartMode::Always => true,
+        };
+        wanted && restarts < self.max_retries
+    }
+
+    // Delay before restart number restarts + 1
+    pub fn backoff(&self, restarts: usize) -> usize {
+        self.backoff_ms.saturating_mul(1 << restarts.min(16))
+    }
+}
+
+impl ServiceInner {
+    // Whether a task or a pool serves the name
+    pub fn is_bound(&self) -> bool {
+        self.pid.is_some() || self.pool.is_some()
+    }
+
+    pub fn serves(&self, pid: usize) -> bool {
+        self.pid == Some(pid) || self.pool.as_ref().map_or(false, |pool| pool.contains(pid))
+    }
 }
 
 impl Service {
@@ -11,25 +92,66 @@ impl Service {
         Self {
             path,
             parent: None,
-            child: Vec::new(),
+            inner: Mutex::new(ServiceInner {
+                child: Vec::new(),
+                pid: None,
+                endpoint: None,
+                binary: None,
+                restart: RestartPolicy::default(),
+                restarts: 0,
+                priority: DEFAULT_PRIORITY,
+                watchdog: None,
+                pool: None,
+                from_manifest: false,
+            }),
         }
     }
 
-    #[allow(dead_code)]
-    pub fn add_child(&mut self, another: Arc<Service>) {
-        self.child.push(another.clone())
+    // Create a node one segment below parent
+    pub fn new_child(parent: &Arc<Service>, segment: &str) -> Arc<Self> {
+        let path = if parent.path.is_empty() {
+            String::from(segment)
+        } else {
+            alloc::format!("{}.{}", parent.path, segment)
+        };
+        let mut service = Self::new(path);
+        service.parent = Some(Arc::downgrade(parent));
+        Arc::new(service)
     }
 
-    #[allow(dead_code)]
-    pub fn remove_child(&mut self, another: Arc<Service>) {
-        let mut index = -1isize;
-        for (i, s) in self.child.iter().enumerate() {
-            if s.path == another.path {
-                index = i as isize;
-            }
-        }
-        if index != -1 {
-            self.child.remove(index as usize);
+    pub fn acquire_inner_lock(&self) -> MutexGuard<ServiceInner> {
+        self.inner.lock()
+    }
+
+    // The last segment of the path
+    pub fn name(&self) -> &str {
+        self.path.rsplit('.').next().unwrap()
+    }
+
+    // Split the path into its segments, None if one of them is empty
+    pub fn segments(&self) -> Option<Vec<&str>> {
+        let segments: Vec<&str> = self.path.split('.').collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return None;
         }
+        Some(segments)
+    }
+
+    pub fn find_child(&self, name: &str) -> Option<Arc<Service>> {
+        self.acquire_inner_lock()
+            .child
+            .iter()
+            .find(|s| s.name() == name)
+            .cloned()
+    }
+
+    pub fn add_child(&self, another: Arc<Service>) {
+        self.acquire_inner_lock().child.push(another)
+    }
+
+    pub fn remove_child(&self, another: &Service) {
+        self.acquire_inner_lock()
+            .child
+            .retain(|s| s.path != another.path);
     }
-}
\ No newline at end of file
+}
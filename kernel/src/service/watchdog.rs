@@ -0,0 +1,134 @@
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use kernel_hal::timer::get_time_ms;
+use crate::errno::{EACCES, ENOENT};
+use crate::ipc::MessagePacket;
+use crate::task::kill_task;
+use crate::timer::{add_timer, cancel_timer, TimerId};
+use super::{Registry, Service, REGISTRY};
+
+/// What happens when a service misses its heartbeat.
+pub enum WatchdogAction {
+    Log,
+    // kill the task, which fails with EXIT_KILLED, so that the restart
+    // policy of the service starts it again
+    Restart,
+    // send the path of the service to the mailbox of the supervisor service,
+    // with the pid of the hung task as the sender
+    Notify(String),
+}
+
+pub struct Watchdog {
+    pub interval_ms: usize,
+    pub action: WatchdogAction,
+    // expires at the end of the current interval, re-armed by every heartbeat
+    pub timer: TimerId,
+}
+
+// Expire the watchdog of node after interval_ms
+fn arm(node: &Arc<Service>, interval_ms: usize) -> TimerId {
+    let service = Arc::downgrade(node);
+    add_timer(
+        get_time_ms().saturating_add(interval_ms),
+        Box::new(move || {
+            if let Some(service) = service.upgrade() {
+                REGISTRY.watchdog_expired(service);
+            }
+        }),
+    )
+}
+
+impl Registry {
+    // Expect task pid, registered as service.path, to call heartbeat at least every
+    // interval_ms. An interval of 0 stops watching the service.
+    pub fn set_watchdog(
+        &self,
+        pid: usize,
+        service: &Service,
+        interval_ms: usize,
+        action: WatchdogAction,
+    ) -> Result<(), isize> {
+        let node = self.find(service).ok_or(ENOENT)?;
+        let mut inner = node.acquire_inner_lock();
+        match inner.pid {
+            Some(owner) if owner == pid => {}
+            Some(_) => return Err(EACCES),
+            None => return Err(ENOENT),
+        }
+        if let Some(watchdog) = inner.watchdog.take() {
+            cancel_timer(watchdog.timer);
+        }
+        if interval_ms != 0 {
+            let timer = arm(&node, interval_ms);
+            inner.watchdog = Some(Watchdog { interval_ms, action, timer });
+        }
+        Ok(())
+    }
+
+    // Restart the intervals of the watched services of task pid,
+    // false if it has none
+    pub fn heartbeat(&self, pid: usize) -> bool {
+        let mut watched = false;
+        for node in self.find_bound(pid) {
+            let mut inner = node.acquire_inner_lock();
+            if let Some(watchdog) = inner.watchdog.as_mut() {
+                cancel_timer(watchdog.timer);
+                watchdog.timer = arm(&node, watchdog.interval_ms);
+                watched = true;
+            }
+        }
+        watched
+    }
+
+    fn watchdog_expired(&self, node: Arc<Service>) {
+        let mut inner = node.acquire_inner_lock();
+        let pid = match inner.pid {
+            Some(pid) => pid,
+            None => return,
+        };
+        let watchdog = match inner.watchdog.as_ref() {
+            Some(watchdog) => watchdog,
+            None => return,
+        };
+        let interval_ms = watchdog.interval_ms;
+        let restart = matches!(watchdog.action, WatchdogAction::Restart);
+        let supervisor = match &watchdog.action {
+            WatchdogAction::Notify(supervisor) => Some(supervisor.clone()),
+            _ => None,
+        };
+        println!("[kernel] watchdog: service {} (pid {}) missed its heartbeat", node.path, pid);
+        if restart {
+            inner.watchdog = None;
+            drop(inner);
+            kill_task(pid);
+            return;
+        }
+        // keep reporting once per missed interval
+        inner.watchdog.as_mut().unwrap().timer = arm(&node, interval_ms);
+        drop(inner);
+        if let Some(supervisor) = supervisor {
+            self.notify_supervisor(&supervisor, &node.path, pid);
+        }
+    }
+
+    fn notify_supervisor(&self, supervisor: &str, path: &str, pid: usize) {
+        let channel = match self.connect(&Service::new(String::from(supervisor))) {
+            Some(channel) => channel,
+            None => {
+                println!("[kernel] watchdog: supervisor {} not found", supervisor);
+                return;
+            }
+        };
+        let msg = MessagePacket {
+            data: Arc::from(path.as_bytes()),
+            sender: pid,
+            handles: Vec::new(),
+            txid: 0,
+            is_reply: false,
+            priority: 0,
+            grant: None,
+        };
+        if channel.write_msg(msg).is_err() {
+            println!("[kernel] watchdog: failed to notify {}", supervisor);
+        }
+    }
+}
@@ -0,0 +1,781 @@
+use alloc::{sync::Arc, vec::Vec};
+use kernel_hal::{timer::get_time_ms, VirtAddr};
+
+use crate::errno::{EACCES, EAGAIN, EBADF, EEXIST, EINVAL, EMSGSIZE, ENOENT, ENOMEM, EPIPE, ETIMEDOUT};
+use crate::fs::File;
+use crate::ipc::{
+    Channel, ChannelLimits, ChannelStats, Handle, KernelObject, MessageInfo, MessagePacket,
+    OverflowPolicy, RecvError, Rights, SendError, Signals, Timer, Topic,
+};
+use crate::mm::{translated_byte_buffer, translated_refmut, translated_str};
+use crate::service::{may_send, REGISTRY, Service};
+use crate::task::{current_killed, current_task, current_user_token, WaitQueue};
+use crate::timer::deadline_after;
+
+/// Return EAGAIN instead of blocking when no message is queued.
+const CHANNEL_NONBLOCK: usize = 1 << 0;
+
+/// Handles a single message may carry.
+const MAX_HANDLES_PER_MSG: usize = 64;
+
+/// User buffers a message is read into.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MessageBuffer {
+    pub bytes: *mut u8,
+    pub num_bytes: usize,
+    pub handles: *mut usize,
+    pub num_handles: usize,
+}
+
+// Get the channel behind a handle of the current task, checking its rights
+fn get_channel(handle: usize, rights: Rights) -> Result<Arc<Channel>, isize> {
+    let task = current_task().unwrap();
+    let inner = task.acquire_inner_lock();
+    inner.get_handle(handle).ok_or(EBADF)?.channel(rights)
+}
+
+// Get the channel to send to through a handle of the current task. Handles
+// connected to a service are checked against the access policy, a violation
+// is logged and fails with EACCES, and dispatched over the pool serving it.
+fn get_send_channel(handle: usize) -> Result<Arc<Channel>, isize> {
+    let task = current_task().unwrap();
+    let inner = task.acquire_inner_lock();
+    let handle = inner.get_handle(handle).ok_or(EBADF)?;
+    let channel = handle.channel(Rights::WRITE)?;
+    let service = match handle.service.clone() {
+        Some(service) => service,
+        None => return Ok(channel),
+    };
+    let app = inner.name.clone();
+    drop(inner);
+    if !may_send(task.getpid(), &app, &service) {
+        println!(
+            "[kernel] access denied: pid {} ({}) may not send to {}",
+            task.getpid(),
+            app,
+            service
+        );
+        return Err(EACCES);
+    }
+    // a pool of instances serves the service, each message may go elsewhere
+    Ok(REGISTRY.dispatch(&service).unwrap_or(channel))
+}
+
+// Map a failed send to the error code of the syscall
+fn send_error(err: SendError) -> isize {
+    match err {
+        SendError::PeerClosed => EPIPE,
+        SendError::BadTxid => EINVAL,
+        SendError::ShouldWait => EAGAIN,
+        SendError::TooLarge => EMSGSIZE,
+        SendError::TimedOut => ETIMEDOUT,
+    }
+}
+
+/// Create a channel and store the handles of its two endpoints in handles[0] and handles[1].
+/// Each endpoint queues at most limits.max_msgs messages of limits.max_bytes bytes in total,
+/// a null limits selects the defaults.
+pub fn sys_channel_create(handles: *mut usize, limits: *const ChannelLimits) -> isize {
+    let token = current_user_token();
+    let limits = if limits.is_null() {
+        ChannelLimits::default()
+    } else {
+        *translated_refmut(token, limits as *mut ChannelLimits)
+    };
+    if limits.max_msgs == 0 {
+        return EINVAL;
+    }
+    let (channel0, channel1) = Channel::create(limits);
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    let handle0 = inner.alloc_handle(Handle::new(
+        KernelObject::Channel(channel0),
+        Rights::channel_default(),
+    ));
+    let handle1 = inner.alloc_handle(Handle::new(
+        KernelObject::Channel(channel1),
+        Rights::channel_default(),
+    ));
+    drop(inner);
+    *translated_refmut(token, handles) = handle0;
+    *translated_refmut(token, unsafe { handles.add(1) }) = handle1;
+    0
+}
+
+/// Return a write-only handle to the mailbox of the task registered as service_path,
+/// or to the channel it published under that name. With the user-space name
+/// server running, the handle is the one it hands out for the name.
+pub fn sys_service_connect(service_path: *const u8) -> isize {
+    let token = current_user_token();
+    let service_path = translated_str(token, service_path);
+    #[cfg(feature = "user-nameserver")]
+    if let Some(result) = crate::service::nameserver_lookup(&service_path) {
+        let handle = match result {
+            Ok((_, handle)) => handle,
+            Err(err) => return err,
+        };
+        return current_task().unwrap().acquire_inner_lock().alloc_handle(handle) as isize;
+    }
+    let mailbox = match REGISTRY.connect(&Service::new(service_path.clone())) {
+        Some(mailbox) => mailbox,
+        None => return ENOENT,
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    inner.alloc_handle(
+        Handle::new(
+            KernelObject::Channel(mailbox),
+            Rights::WRITE | Rights::DUPLICATE | Rights::TRANSFER,
+        )
+        .for_service(service_path),
+    ) as isize
+}
+
+/// Copy a handle, the copy may only carry a subset of the original rights.
+pub fn sys_handle_duplicate(handle: usize, rights: u32) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    let mut new_handle = match inner.get_handle(handle) {
+        Some(handle) => handle.clone(),
+        None => return EBADF,
+    };
+    if let Err(err) = new_handle.check_rights(Rights::DUPLICATE) {
+        return err;
+    }
+    let rights = Rights::from_bits_truncate(rights);
+    if let Err(err) = new_handle.check_rights(rights) {
+        return err;
+    }
+    new_handle.rights = rights;
+    inner.alloc_handle(new_handle) as isize
+}
+
+/// Close a handle, the last handle to a channel endpoint signals PEER_CLOSED on the other one.
+pub fn sys_handle_close(handle: usize) -> isize {
+    let task = current_task().unwrap();
+    let removed = task.acquire_inner_lock().remove_handle(handle);
+    match removed {
+        Some(_) => 0,
+        None => EBADF,
+    }
+}
+
+// Move the listed handles out of the current task to be sent through channel,
+// either all of them or none
+fn take_handles(
+    channel_handle: usize,
+    channel: &Arc<Channel>,
+    idxs: &[usize],
+) -> Result<Vec<Handle>, isize> {
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    for (i, idx) in idxs.iter().enumerate() {
+        // an endpoint cannot travel through itself, and a handle moves only once
+        if *idx == channel_handle || idxs[..i].contains(idx) {
+            return Err(EINVAL);
+        }
+        let handle = inner.get_handle(*idx).ok_or(EBADF)?;
+        handle.check_rights(Rights::TRANSFER)?;
+        // nor through its peer, queued in its own receive queue it would keep
+        // itself alive and never signal PEER_CLOSED
+        if let KernelObject::Channel(endpoint) = &handle.object {
+            if Arc::ptr_eq(endpoint, channel) || channel.is_peer(endpoint) {
+                return Err(EINVAL);
+            }
+        }
+    }
+    Ok(idxs.iter().map(|idx| inner.remove_handle(*idx).unwrap()).collect())
+}
+
+// Build a message from the user buffers of the current task, moving the listed handles into it
+fn read_user_msg(
+    channel_handle: usize,
+    channel: &Arc<Channel>,
+    buf: *const u8,
+    len: usize,
+    handles: *const usize,
+    num_handles: usize,
+) -> Result<MessagePacket, isize> {
+    if num_handles > MAX_HANDLES_PER_MSG {
+        return Err(EINVAL);
+    }
+    let token = current_user_token();
+    let mut data: Vec<u8> = Vec::with_capacity(len);
+    for buffer in translated_byte_buffer(token, buf, len) {
+        data.extend_from_slice(buffer);
+    }
+    let mut handle_idxs: Vec<usize> = Vec::with_capacity(num_handles);
+    for i in 0..num_handles {
+        handle_idxs.push(*translated_refmut(token, unsafe { handles.add(i) } as *mut usize));
+    }
+    #[allow(unused_mut)]
+    let mut handles = take_handles(channel_handle, channel, &handle_idxs)?;
+    #[cfg(feature = "user-nameserver")]
+    crate::service::label_registration(channel, &data, &mut handles);
+    Ok(MessagePacket {
+        data: data.into(),
+        sender: current_task().unwrap().getpid(),
+        handles,
+        txid: 0,
+        is_reply: false,
+        priority: 0,
+        grant: None,
+    })
+}
+
+// Copy a received message into buffer and install its handles and granted pages in the current task
+fn write_user_msg(message_packet: MessagePacket, buffer: &MessageBuffer, info: *mut MessageInfo) -> isize {
+    let token = current_user_token();
+    let mut message_info = message_packet.info();
+    let MessagePacket { data, handles, grant, .. } = message_packet;
+    let mut start = 0;
+    for bytes in translated_byte_buffer(token, buffer.bytes, data.len()) {
+        bytes.copy_from_slice(&data[start..start + bytes.len()]);
+        start += bytes.len();
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    // read_msg made sure there is room before the message left the queue
+    if let Some(area) = grant {
+        match inner.map_granted_area(area) {
+            Ok(start) => message_info.grant_addr = start.0,
+            Err(err) => return err,
+        }
+    }
+    for (i, transferred) in handles.into_iter().enumerate() {
+        let idx = inner.alloc_handle(transferred);
+        *translated_refmut(token, unsafe { buffer.handles.add(i) }) = idx;
+    }
+    drop(inner);
+    if !info.is_null() {
+        *translated_refmut(token, info) = message_info;
+    }
+    data.len() as isize
+}
+
+/// Send buf through the channel behind handle. The handles listed in
+/// handles[..num_handles] leave the handle table of the caller and travel with the message,
+/// at most MAX_HANDLES_PER_MSG of them or EINVAL is returned.
+/// Return EPIPE if the other endpoint is closed, the listed handles are closed as well.
+/// When the queue of the other endpoint is full, block until it drains, or return
+/// EAGAIN if flags has CHANNEL_NONBLOCK. A message larger than the queue gets EMSGSIZE.
+pub fn sys_channel_write(
+    handle: usize,
+    buf: *const u8,
+    len: usize,
+    handles: *const usize,
+    num_handles: usize,
+    flags: usize,
+) -> isize {
+    let channel = match get_send_channel(handle) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    // wait before the handles leave the caller, so that a failure keeps them
+    if let Err(err) = channel.wait_writable(len, flags & CHANNEL_NONBLOCK != 0, usize::MAX) {
+        return send_error(err);
+    }
+    let message_packet = match read_user_msg(handle, &channel, buf, len, handles, num_handles) {
+        Ok(m) => m,
+        Err(err) => return err,
+    };
+    match channel.write_msg(message_packet) {
+        Ok(()) => 0,
+        Err(err) => send_error(err),
+    }
+}
+
+/// Send buf through the channel behind handle together with the pages of the
+/// mapping [grant_addr, grant_addr + grant_len), which must have been created
+/// as a whole by mmap. The pages are unmapped from the caller and mapped into
+/// the receiver when it reads the message, without copying them.
+/// Errors are those of channel_write, or EINVAL if there is no such mapping or
+/// the range wraps around. The pages stay with the caller on every error.
+pub fn sys_channel_grant(
+    handle: usize,
+    buf: *const u8,
+    len: usize,
+    grant_addr: usize,
+    grant_len: usize,
+    flags: usize,
+) -> isize {
+    let channel = match get_send_channel(handle) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    let grant_end = match grant_addr.checked_add(grant_len) {
+        Some(grant_end) => grant_end,
+        None => return EINVAL,
+    };
+    let start_va: VirtAddr = grant_addr.into();
+    let end_va: VirtAddr = grant_end.into();
+    if !start_va.aligned() || grant_len == 0 {
+        return EINVAL;
+    }
+    // wait before the pages leave the caller, so that a failure keeps them
+    if let Err(err) = channel.wait_writable(len, flags & CHANNEL_NONBLOCK != 0, usize::MAX) {
+        return send_error(err);
+    }
+    let mut message_packet = match read_user_msg(handle, &channel, buf, len, core::ptr::null(), 0) {
+        Ok(m) => m,
+        Err(err) => return err,
+    };
+    let task = current_task().unwrap();
+    let area = task.acquire_inner_lock().take_framed_area(start_va, end_va);
+    match area {
+        Some(area) => message_packet.grant = Some(area),
+        None => return EINVAL,
+    }
+    match channel.write_msg_or_return(message_packet) {
+        Ok(()) => 0,
+        Err((err, message_packet)) => {
+            // the pages stay with the caller
+            if let Some(area) = message_packet.grant {
+                task.acquire_inner_lock().restore_framed_area(area);
+            }
+            send_error(err)
+        }
+    }
+}
+
+/// Return the length of the message copied into buffer, its handles are installed
+/// in the handle table of the caller and their values stored in buffer.handles.
+/// If either buffer is too short, return EMSGSIZE and keep the message queued.
+/// If the caller has no free range to map the pages granted by the message, return
+/// ENOMEM and keep it queued as well.
+/// In both cases the length, sender, handle count and transaction id of the message
+/// are stored in info, if given. A nonzero transaction id asks for a channel_reply.
+/// Pages granted with the message are mapped into the caller at info.grant_addr.
+/// Once the queue is drained and the other endpoint is closed, return EPIPE.
+pub fn sys_channel_read(
+    handle: usize,
+    buffer: *const MessageBuffer,
+    flags: usize,
+    info: *mut MessageInfo,
+) -> isize {
+    let token = current_user_token();
+    let buffer = *translated_refmut(token, buffer as *mut MessageBuffer);
+    let channel = match get_channel(handle, Rights::READ) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    let message_packet = match channel.recv_msg(
+        buffer.num_bytes,
+        buffer.num_handles,
+        flags & CHANNEL_NONBLOCK != 0,
+    ) {
+        Ok(m) => m,
+        Err(RecvError::ShouldWait) => return EAGAIN,
+        Err(RecvError::PeerClosed) => return EPIPE,
+        Err(RecvError::NoMemory) => return ENOMEM,
+        Err(RecvError::BufferTooSmall(message_info)) => {
+            if !info.is_null() {
+                *translated_refmut(token, info) = message_info;
+            }
+            return EMSGSIZE;
+        }
+    };
+    write_user_msg(message_packet, &buffer, info)
+}
+
+/// Arguments of channel_call, too many to pass in registers.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CallArgs {
+    pub wr_bytes: *const u8,
+    pub wr_num_bytes: usize,
+    pub wr_handles: *const usize,
+    pub wr_num_handles: usize,
+    pub rd: MessageBuffer,
+    // usize::MAX waits forever
+    pub timeout_ms: usize,
+}
+
+/// Send a request through the channel behind handle and block until the server
+/// answers it with channel_reply, then read the reply like channel_read does.
+/// Return ETIMEDOUT if there was no room for the request or no reply within timeout_ms, EPIPE if the server
+/// closed its endpoint before answering. A reply which does not fit into the read
+/// buffers is kept and EMSGSIZE returned, with info filled in; collect it with
+/// channel_take_reply.
+pub fn sys_channel_call(handle: usize, args: *const CallArgs, info: *mut MessageInfo) -> isize {
+    let token = current_user_token();
+    let args = *translated_refmut(token, args as *mut CallArgs);
+    let channel = match get_send_channel(handle) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    let deadline_ms = deadline_after(args.timeout_ms);
+    if let Err(err) = channel.wait_writable(args.wr_num_bytes, false, deadline_ms) {
+        return send_error(err);
+    }
+    let request = match read_user_msg(
+        handle,
+        &channel,
+        args.wr_bytes,
+        args.wr_num_bytes,
+        args.wr_handles,
+        args.wr_num_handles,
+    ) {
+        Ok(m) => m,
+        Err(err) => return err,
+    };
+    let reply = match channel.call_msg(request, deadline_ms) {
+        Ok(reply) => reply,
+        Err(err) => return send_error(err),
+    };
+    if reply.data.len() > args.rd.num_bytes || reply.handles.len() > args.rd.num_handles {
+        if !info.is_null() {
+            *translated_refmut(token, info) = reply.info();
+        }
+        // kept behind handle itself, whichever instance of a pool answered
+        if let Ok(endpoint) = get_channel(handle, Rights::WRITE) {
+            endpoint.keep_reply(reply);
+        }
+        return EMSGSIZE;
+    }
+    write_user_msg(reply, &args.rd, info)
+}
+
+/// Read the reply of call txid which channel_call kept because it did not fit,
+/// like channel_read does. Return EMSGSIZE with info filled in if it still does
+/// not fit, and EINVAL if no reply is kept for txid on the channel behind handle.
+pub fn sys_channel_take_reply(
+    handle: usize,
+    txid: usize,
+    buffer: *const MessageBuffer,
+    info: *mut MessageInfo,
+) -> isize {
+    let token = current_user_token();
+    let buffer = *translated_refmut(token, buffer as *mut MessageBuffer);
+    let channel = match get_channel(handle, Rights::WRITE) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    match channel.take_reply(txid, buffer.num_bytes, buffer.num_handles) {
+        Some(Ok(reply)) => write_user_msg(reply, &buffer, info),
+        Some(Err(message_info)) => {
+            if !info.is_null() {
+                *translated_refmut(token, info) = message_info;
+            }
+            EMSGSIZE
+        }
+        None => EINVAL,
+    }
+}
+
+/// Answer the request with transaction id txid, read from the channel behind handle.
+/// The reply goes back to the endpoint the request was sent through. Return
+/// EMSGSIZE, with the request still unanswered, if the reply is larger than the
+/// queue of that endpoint.
+pub fn sys_channel_reply(
+    handle: usize,
+    txid: usize,
+    buf: *const u8,
+    len: usize,
+    handles: *const usize,
+    num_handles: usize,
+) -> isize {
+    let channel = match get_channel(handle, Rights::READ) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    if len > channel.max_bytes() {
+        return EMSGSIZE;
+    }
+    let message_packet = match read_user_msg(handle, &channel, buf, len, handles, num_handles) {
+        Ok(m) => m,
+        Err(err) => return err,
+    };
+    // the handles of a rejected reply are dropped with it
+    match channel.reply_msg(txid, message_packet) {
+        Ok(()) => 0,
+        Err(err) => send_error(err),
+    }
+}
+
+/// Store the occupancy and high-water marks of the receive queue of the endpoint behind handle.
+pub fn sys_channel_stats(handle: usize, stats: *mut ChannelStats) -> isize {
+    let token = current_user_token();
+    let channel = match get_channel(handle, Rights::empty()) {
+        Ok(channel) => channel,
+        Err(err) => return err,
+    };
+    *translated_refmut(token, stats) = channel.stats();
+    0
+}
+
+/// Block until the object behind handle asserts one of signals, or timeout_ms passes.
+/// The signals asserted when returning are stored in observed, if given.
+/// Return ETIMEDOUT if none of the requested signals came up in time, and EINVAL
+/// without blocking if they are not asserted and the object never changes them.
+pub fn sys_object_wait_one(handle: usize, signals: u32, timeout_ms: usize, observed: *mut u32) -> isize {
+    let token = current_user_token();
+    let object = {
+        let task = current_task().unwrap();
+        let inner = task.acquire_inner_lock();
+        match inner.get_handle(handle) {
+            Some(handle) => handle.object.clone(),
+            None => return EBADF,
+        }
+    };
+    let signals = Signals::from_bits_truncate(signals);
+    let deadline_ms = deadline_after(timeout_ms);
+    let mut timed_out = false;
+    let asserted = loop {
+        let asserted = object.signals();
+        if asserted.intersects(signals) || timed_out {
+            break asserted;
+        }
+        let queue = match object.wait_queue() {
+            Some(queue) => queue,
+            None => return EINVAL,
+        };
+        timed_out = !WaitQueue::wait_any(&[queue], deadline_ms);
+    };
+    if !observed.is_null() {
+        *translated_refmut(token, observed) = asserted.bits();
+    }
+    if asserted.intersects(signals) {
+        0
+    } else {
+        ETIMEDOUT
+    }
+}
+
+/// Create a timer object, it asserts TIMER_FIRED once set and expired.
+pub fn sys_timer_create() -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    inner.alloc_handle(Handle::new(
+        KernelObject::Timer(Timer::new()),
+        Rights::WRITE | Rights::DUPLICATE | Rights::TRANSFER,
+    )) as isize
+}
+
+/// Arm the timer behind handle to fire timeout_ms from now, clearing TIMER_FIRED.
+pub fn sys_timer_set(handle: usize, timeout_ms: usize) -> isize {
+    match get_timer(handle, Rights::WRITE) {
+        Ok(timer) => {
+            timer.set(deadline_after(timeout_ms));
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+/// Disarm the timer behind handle and clear TIMER_FIRED.
+pub fn sys_timer_cancel(handle: usize) -> isize {
+    match get_timer(handle, Rights::WRITE) {
+        Ok(timer) => {
+            timer.cancel();
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+fn get_timer(handle: usize, rights: Rights) -> Result<Arc<Timer>, isize> {
+    let task = current_task().unwrap();
+    let inner = task.acquire_inner_lock();
+    inner.get_handle(handle).ok_or(EBADF)?.timer(rights)
+}
+
+/// WaitItem.kind of an entry naming a handle.
+const WAIT_HANDLE: u32 = 0;
+/// WaitItem.kind of an entry naming a file descriptor.
+const WAIT_FD: u32 = 1;
+
+// Objects without a wait queue, such as stdin, are polled this often
+const WAIT_POLL_INTERVAL_MS: usize = 10;
+
+/// One entry of object_wait_many, observed is written by the kernel.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct WaitItem {
+    pub kind: u32,
+    pub signals: u32,
+    pub id: usize,
+    pub observed: u32,
+}
+
+enum Waitable {
+    Object(KernelObject),
+    File(Arc<dyn File + Send + Sync>),
+}
+
+impl Waitable {
+    fn signals(&self) -> Signals {
+        match self {
+            Waitable::Object(object) => object.signals(),
+            Waitable::File(file) => file.signals(),
+        }
+    }
+
+    fn wait_queue(&self) -> Option<&WaitQueue> {
+        match self {
+            Waitable::Object(object) => object.wait_queue(),
+            Waitable::File(file) => file.wait_queue(),
+        }
+    }
+
+    // A file without a wait queue is polled, an object without one never changes
+    fn is_polled(&self) -> bool {
+        matches!(self, Waitable::File(_)) && self.wait_queue().is_none()
+    }
+}
+
+/// Block until any of the count handles or file descriptors in items asserts
+/// one of its signals, or timeout_ms passes. The asserted signals of every
+/// entry are stored in its observed field. Return the number of entries which
+/// fired, or ETIMEDOUT if none did in time. Return EINVAL if count exceeds the
+/// handles and file descriptors of the caller together, or if none fired and
+/// none of the entries can ever change its signals.
+pub fn sys_object_wait_many(items: *mut WaitItem, count: usize, timeout_ms: usize) -> isize {
+    let token = current_user_token();
+    {
+        let task = current_task().unwrap();
+        let inner = task.acquire_inner_lock();
+        if count > inner.handle_table.len() + inner.fd_table.len() {
+            return EINVAL;
+        }
+    }
+    let mut wait_items: Vec<WaitItem> = Vec::with_capacity(count);
+    for i in 0..count {
+        wait_items.push(*translated_refmut(token, unsafe { items.add(i) }));
+    }
+    let mut waitables: Vec<Waitable> = Vec::with_capacity(count);
+    {
+        let task = current_task().unwrap();
+        let inner = task.acquire_inner_lock();
+        for item in wait_items.iter() {
+            let waitable = match item.kind {
+                WAIT_HANDLE => inner
+                    .get_handle(item.id)
+                    .map(|handle| Waitable::Object(handle.object.clone())),
+                WAIT_FD => inner.fd_table.get(item.id).cloned().flatten().map(Waitable::File),
+                _ => return EINVAL,
+            };
+            match waitable {
+                Some(waitable) => waitables.push(waitable),
+                None => return EBADF,
+            }
+        }
+    }
+    let deadline_ms = deadline_after(timeout_ms);
+    let polled = waitables.iter().any(Waitable::is_polled);
+    let changing = polled || waitables.iter().any(|waitable| waitable.wait_queue().is_some());
+    let mut fired;
+    loop {
+        fired = 0;
+        for (item, waitable) in wait_items.iter_mut().zip(waitables.iter()) {
+            let asserted = waitable.signals();
+            item.observed = asserted.bits();
+            if asserted.intersects(Signals::from_bits_truncate(item.signals)) {
+                fired += 1;
+            }
+        }
+        if fired > 0 || get_time_ms() >= deadline_ms {
+            break;
+        }
+        if !changing {
+            return EINVAL;
+        }
+        let queues: Vec<&WaitQueue> = waitables.iter().filter_map(Waitable::wait_queue).collect();
+        let wake_ms = if polled {
+            deadline_ms.min(get_time_ms() + WAIT_POLL_INTERVAL_MS)
+        } else {
+            deadline_ms
+        };
+        if !WaitQueue::wait_any(&queues, wake_ms) && current_killed() {
+            break;
+        }
+    }
+    for (i, item) in wait_items.iter().enumerate() {
+        translated_refmut(token, unsafe { items.add(i) }).observed = item.observed;
+    }
+    if fired > 0 {
+        fired as isize
+    } else {
+        ETIMEDOUT
+    }
+}
+
+/// topic_subscribe policy discarding the oldest queued message when the queue is full.
+const TOPIC_DROP_OLDEST: usize = 0;
+/// topic_subscribe policy discarding the message being published when the queue is full.
+const TOPIC_DROP_NEWEST: usize = 1;
+
+/// Return a handle to publish on the topic named topic_path, creating the topic
+/// if needed. Return EEXIST if a service is registered under that name.
+pub fn sys_topic_open(topic_path: *const u8) -> isize {
+    let token = current_user_token();
+    let topic_path = translated_str(token, topic_path);
+    let topic = match REGISTRY.open_topic(&Service::new(topic_path)) {
+        Some(topic) => topic,
+        None => return EEXIST,
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    inner.alloc_handle(Handle::new(
+        KernelObject::Topic(topic),
+        Rights::WRITE | Rights::DUPLICATE | Rights::TRANSFER,
+    )) as isize
+}
+
+/// Subscribe to the topic named topic_path and return a handle to read the published
+/// messages from, queued under limits (or the defaults if null) and the given overflow
+/// policy. Closing the handle, or exiting, unsubscribes.
+pub fn sys_topic_subscribe(topic_path: *const u8, policy: usize, limits: *const ChannelLimits) -> isize {
+    let token = current_user_token();
+    let topic_path = translated_str(token, topic_path);
+    let policy = match policy {
+        TOPIC_DROP_OLDEST => OverflowPolicy::DropOldest,
+        TOPIC_DROP_NEWEST => OverflowPolicy::DropNewest,
+        _ => return EINVAL,
+    };
+    let limits = if limits.is_null() {
+        ChannelLimits::default()
+    } else {
+        *translated_refmut(token, limits as *mut ChannelLimits)
+    };
+    if limits.max_msgs == 0 {
+        return EINVAL;
+    }
+    let topic = match REGISTRY.open_topic(&Service::new(topic_path)) {
+        Some(topic) => topic,
+        None => return EEXIST,
+    };
+    let reader = topic.subscribe(policy, limits);
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    inner.alloc_handle(Handle::new(
+        KernelObject::Channel(reader),
+        Rights::READ | Rights::DUPLICATE | Rights::TRANSFER,
+    )) as isize
+}
+
+/// Queue a copy of buf for every subscriber of the topic behind handle, without
+/// blocking. Return the number of subscribers whose queue accepted it, EMSGSIZE
+/// if buf is larger than the queue of every subscriber.
+pub fn sys_topic_publish(handle: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let topic = match get_topic(handle, Rights::WRITE) {
+        Ok(topic) => topic,
+        Err(err) => return err,
+    };
+    if len > topic.max_bytes() {
+        return EMSGSIZE;
+    }
+    let mut data: Vec<u8> = Vec::with_capacity(len);
+    for buffer in translated_byte_buffer(token, buf, len) {
+        data.extend_from_slice(buffer);
+    }
+    topic.publish(data.into(), current_task().unwrap().getpid()) as isize
+}
+
+fn get_topic(handle: usize, rights: Rights) -> Result<Arc<Topic>, isize> {
+    let task = current_task().unwrap();
+    let inner = task.acquire_inner_lock();
+    inner.get_handle(handle).ok_or(EBADF)?.topic(rights)
+}
@@ -2,7 +2,10 @@ const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_GET_PRIORITY: usize = 141;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_MUNMAP: usize = 215;
@@ -15,32 +18,107 @@ const SYSCALL_MMAP_CREATE: usize = 401;
 const SYSCALL_SERVICE_REGISTER: usize = 500;
 const SYSCALL_CHANNEL_READ: usize = 501;
 const SYSCALL_CHANNEL_WRITE: usize = 502;
+const SYSCALL_CHANNEL_CREATE: usize = 503;
+const SYSCALL_SERVICE_CONNECT: usize = 504;
+const SYSCALL_HANDLE_DUPLICATE: usize = 505;
+const SYSCALL_HANDLE_CLOSE: usize = 506;
+const SYSCALL_CHANNEL_CALL: usize = 507;
+const SYSCALL_CHANNEL_REPLY: usize = 508;
+const SYSCALL_OBJECT_WAIT_ONE: usize = 509;
+const SYSCALL_CHANNEL_STATS: usize = 510;
+const SYSCALL_TIMER_CREATE: usize = 511;
+const SYSCALL_TIMER_SET: usize = 512;
+const SYSCALL_TIMER_CANCEL: usize = 513;
+const SYSCALL_OBJECT_WAIT_MANY: usize = 514;
+const SYSCALL_CHANNEL_GRANT: usize = 515;
+const SYSCALL_TOPIC_OPEN: usize = 516;
+const SYSCALL_TOPIC_SUBSCRIBE: usize = 517;
+const SYSCALL_TOPIC_PUBLISH: usize = 518;
+const SYSCALL_SERVICE_LIST: usize = 519;
+const SYSCALL_SERVICE_LOOKUP: usize = 520;
+const SYSCALL_SERVICE_WAIT: usize = 521;
+const SYSCALL_SERVICE_PUBLISH: usize = 522;
+const SYSCALL_SERVICE_UNPUBLISH: usize = 523;
+const SYSCALL_ACCESS_CHECK: usize = 524;
+const SYSCALL_SERVICE_WATCHDOG: usize = 525;
+const SYSCALL_HEARTBEAT: usize = 526;
+const SYSCALL_SERVICE_JOIN: usize = 527;
+const SYSCALL_SERVICE_LEAVE: usize = 528;
+const SYSCALL_TASK_SET_PERIODIC: usize = 529;
+const SYSCALL_TASK_WAIT_PERIOD: usize = 530;
+const SYSCALL_TASK_PERIODIC_STATS: usize = 531;
+const SYSCALL_SLEEP_UNTIL: usize = 532;
+const SYSCALL_SERVICE_REMOVE: usize = 533;
+const SYSCALL_CHANNEL_TAKE_REPLY: usize = 534;
+const SYSCALL_TASK_PARTITION: usize = 535;
 
 mod fs;
+mod ipc;
 mod process;
+mod service;
 
 use fs::*;
+use ipc::*;
 use process::*;
+use service::*;
+use crate::ipc::{ChannelLimits, ChannelStats, MessageInfo};
+use crate::task::PeriodicStats;
 
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     match syscall_id {
         SYSCALL_CLOSE => sys_close(args[0]),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_NANOSLEEP => sys_nanosleep(args[0]),
         SYSCALL_YIELD => sys_yield(),
+        SYSCALL_SET_PRIORITY => sys_set_priority(args[0], args[1]),
+        SYSCALL_GET_PRIORITY => sys_get_priority(args[0]),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
         SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
         SYSCALL_GETPID => sys_getpid(),
         SYSCALL_FORK => sys_fork(),
         SYSCALL_EXEC => sys_exec(args[0] as *const u8),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32, args[2]),
         SYSCALL_CREATE_TASK => sys_create_task(args[0] as *const u8),
         SYSCALL_MMAP_CREATE => sys_mmap_create(args[0], args[1]),
-        SYSCALL_CHANNEL_READ => sys_channel_read(args[0] as *mut u8, args[1]),
-        SYSCALL_CHANNEL_WRITE => sys_channel_write(args[0] as *const u8, args[1] as *const u8, args[2]),
-        SYSCALL_SERVICE_REGISTER => sys_register(args[0] as *const u8, args[1] as *const u8),
+        SYSCALL_CHANNEL_READ => sys_channel_read(args[0], args[1] as *const MessageBuffer, args[2], args[3] as *mut MessageInfo),
+        SYSCALL_CHANNEL_WRITE => sys_channel_write(args[0], args[1] as *const u8, args[2], args[3] as *const usize, args[4], args[5]),
+        SYSCALL_CHANNEL_CREATE => sys_channel_create(args[0] as *mut usize, args[1] as *const ChannelLimits),
+        SYSCALL_SERVICE_CONNECT => sys_service_connect(args[0] as *const u8),
+        SYSCALL_HANDLE_DUPLICATE => sys_handle_duplicate(args[0], args[1] as u32),
+        SYSCALL_HANDLE_CLOSE => sys_handle_close(args[0]),
+        SYSCALL_CHANNEL_CALL => sys_channel_call(args[0], args[1] as *const CallArgs, args[2] as *mut MessageInfo),
+        SYSCALL_CHANNEL_REPLY => sys_channel_reply(args[0], args[1], args[2] as *const u8, args[3], args[4] as *const usize, args[5]),
+        SYSCALL_OBJECT_WAIT_ONE => sys_object_wait_one(args[0], args[1] as u32, args[2], args[3] as *mut u32),
+        SYSCALL_CHANNEL_STATS => sys_channel_stats(args[0], args[1] as *mut ChannelStats),
+        SYSCALL_TIMER_CREATE => sys_timer_create(),
+        SYSCALL_TIMER_SET => sys_timer_set(args[0], args[1]),
+        SYSCALL_TIMER_CANCEL => sys_timer_cancel(args[0]),
+        SYSCALL_OBJECT_WAIT_MANY => sys_object_wait_many(args[0] as *mut WaitItem, args[1], args[2]),
+        SYSCALL_CHANNEL_GRANT => sys_channel_grant(args[0], args[1] as *const u8, args[2], args[3], args[4], args[5]),
+        SYSCALL_TOPIC_OPEN => sys_topic_open(args[0] as *const u8),
+        SYSCALL_TOPIC_SUBSCRIBE => sys_topic_subscribe(args[0] as *const u8, args[1], args[2] as *const ChannelLimits),
+        SYSCALL_TOPIC_PUBLISH => sys_topic_publish(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_SERVICE_LIST => sys_service_list(args[0] as *const u8, args[1] as *mut u8, args[2], args[3]),
+        SYSCALL_SERVICE_LOOKUP => sys_service_lookup(args[0] as *const u8),
+        SYSCALL_SERVICE_WAIT => sys_service_wait(args[0] as *const u8, args[1]),
+        SYSCALL_SERVICE_PUBLISH => sys_service_publish(args[0] as *const u8, args[1]),
+        SYSCALL_SERVICE_UNPUBLISH => sys_service_unpublish(args[0] as *const u8),
+        SYSCALL_ACCESS_CHECK => sys_access_check(args[0], args[1] as *const u8),
+        SYSCALL_SERVICE_WATCHDOG => sys_service_watchdog(args[0] as *const u8, args[1], args[2], args[3] as *const u8),
+        SYSCALL_HEARTBEAT => sys_heartbeat(),
+        SYSCALL_SERVICE_JOIN => sys_service_join(args[0] as *const u8, args[1]),
+        SYSCALL_SERVICE_LEAVE => sys_service_leave(args[0] as *const u8),
+        SYSCALL_TASK_SET_PERIODIC => sys_set_periodic(args[0], args[1], args[2]),
+        SYSCALL_TASK_WAIT_PERIOD => sys_wait_period(),
+        SYSCALL_TASK_PERIODIC_STATS => sys_periodic_stats(args[0], args[1] as *mut PeriodicStats),
+        SYSCALL_SLEEP_UNTIL => sys_sleep_until(args[0]),
+        SYSCALL_SERVICE_REMOVE => sys_service_remove(args[0] as *const u8),
+        SYSCALL_TASK_PARTITION => sys_task_partition(args[0], args[1] as *mut u8, args[2]),
+        SYSCALL_CHANNEL_TAKE_REPLY => sys_channel_take_reply(args[0], args[1], args[2] as *const MessageBuffer, args[3] as *mut MessageInfo),
+        SYSCALL_SERVICE_REGISTER => sys_register(args[0] as *const u8, args[1] as *const u8, args[2] as *const RestartArgs),
         _ => panic!("Unsupported syscall_id: {}", syscall_id),
     }
 }
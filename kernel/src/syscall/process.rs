@@ -1,16 +1,18 @@
 use alloc::sync::Arc;
 use kernel_hal::VirtAddr;
 
-use crate::mm::{translated_refmut, translated_str};
-use crate::service::Service;
-use crate::task::{PidHandle, alloc_new_frames, check_all_allocated, check_allocated, dealloc_frames, find_free_frames};
+use crate::errno::{EACCES, EINVAL, ENOENT};
+use crate::mm::{translated_byte_buffer, translated_refmut, translated_str};
+use crate::service::{RestartMode, RestartPolicy, Service};
+use crate::task::{alloc_new_frames, check_all_allocated, check_allocated, dealloc_frames, find_free_frames};
 use kernel_hal::{timer::get_time_ms};
 use crate::{
     loader::get_app_data_by_name,
     mm::MapPermission,
     task::{
-        add_task, current_task, current_user_token, exit_current_and_run_next,
-        suspend_current_and_run_next,
+        add_task, current_task, current_user_token, exit_current_and_run_next, find_task,
+        set_task_priority, sleep_current_until, suspend_current_and_run_next, task_partition,
+        Periodic, PeriodicStats, TaskControlBlock, DEFAULT_PRIORITY, INITPROC, MAX_PRIORITY,
     },
     service::REGISTRY,
 };
@@ -106,61 +108,185 @@ pub fn sys_exec(path: *const u8) -> isize {
     let path = translated_str(token, path);
     if let Some(data) = get_app_data_by_name(path.as_str()) {
         let task = current_task().unwrap();
-        task.exec(data);
+        task.exec(path.as_str(), data);
         0
     } else {
         -1
     }
 }
 
+pub const WNOHANG: usize = 1;
+
 /// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+/// Else block until the child exits, or with WNOHANG return -2 if it is still running.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> isize {
     let task = current_task().unwrap();
-    // find a child process
+    loop {
+        // find a child process
 
-    // ---- hold current PCB lock
-    let mut inner = task.acquire_inner_lock();
-    if inner
-        .children
-        .iter()
-        .find(|p| pid == -1 || pid as usize == p.getpid())
-        .is_none()
-    {
-        return -1;
+        // ---- hold current PCB lock
+        let mut inner = task.acquire_inner_lock();
+        if inner
+            .children
+            .iter()
+            .find(|p| pid == -1 || pid as usize == p.getpid())
+            .is_none()
+        {
+            return -1;
+            // ---- release current PCB lock
+        }
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily hold child PCB lock
+            p.acquire_inner_lock().is_zombie() && (pid == -1 || pid as usize == p.getpid())
+            // ++++ release child PCB lock
+        });
+        if let Some((idx, _)) = pair {
+            let child = inner.children.remove(idx);
+            // confirm that child will be deallocated after removing from children list
+            assert_eq!(Arc::strong_count(&child), 1);
+            let found_pid = child.getpid();
+            // ++++ temporarily hold child lock
+            let exit_code = child.acquire_inner_lock().exit_code;
+            // ++++ release child PCB lock
+            *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
+            return found_pid as isize;
+        }
+        if options & WNOHANG != 0 {
+            return -2;
+        }
+        drop(inner);
         // ---- release current PCB lock
+        if !task.child_exited.wait() {
+            // killed, the task exits on its way back to user space
+            return -1;
+        }
     }
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily hold child PCB lock
-        p.acquire_inner_lock().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-        // ++++ release child PCB lock
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily hold child lock
-        let exit_code = child.acquire_inner_lock().exit_code;
-        // ++++ release child PCB lock
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
-    }
-    // ---- release current PCB lock automatically
+}
+
+/// Block the caller for at least duration_ns, rounded up to milliseconds.
+pub fn sys_nanosleep(duration_ns: usize) -> isize {
+    let duration_ms = duration_ns / 1_000_000 + (duration_ns % 1_000_000 != 0) as usize;
+    sleep_current_until(get_time_ms().saturating_add(duration_ms));
+    0
+}
+
+/// Block the caller until sys_get_time reaches deadline_ms.
+pub fn sys_sleep_until(deadline_ms: usize) -> isize {
+    sleep_current_until(deadline_ms);
+    0
 }
 
 pub fn sys_getpid() -> isize {
     current_task().unwrap().pid.0 as isize
 }
 
+// Task pid, or the current task for pid 0
+fn target_task(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    if pid == 0 {
+        current_task()
+    } else {
+        find_task(pid)
+    }
+}
+
+// Whether task may raise priorities above its own: initproc and the
+// supervisors the manifest started, which set up the priorities of the system
+fn may_raise_priority(task: &Arc<TaskControlBlock>) -> bool {
+    Arc::ptr_eq(task, &*INITPROC) || !REGISTRY.manifest_names_of(task.getpid()).is_empty()
+}
+
+/// Set the priority of task pid, 0 for the caller itself, which must be the
+/// task or its parent. A larger value runs first, from 1 up to MAX_PRIORITY.
+/// A caller may give at most its own priority, so a parent can raise a child
+/// up to its level; only initproc and the tasks the manifest started may go
+/// above their own priority, up to MAX_PRIORITY.
+pub fn sys_set_priority(pid: usize, priority: usize) -> isize {
+    if priority == 0 || priority > MAX_PRIORITY {
+        return EINVAL;
+    }
+    let current = current_task().unwrap();
+    let limit = current.acquire_inner_lock().priority;
+    if priority > limit && !may_raise_priority(&current) {
+        return EACCES;
+    }
+    let task = match target_task(pid) {
+        Some(task) => task,
+        None => return ENOENT,
+    };
+    let is_parent = task
+        .acquire_inner_lock()
+        .parent
+        .as_ref()
+        .and_then(|parent| parent.upgrade())
+        .map_or(false, |parent| Arc::ptr_eq(&parent, &current));
+    if !Arc::ptr_eq(&task, &current) && !is_parent {
+        return EACCES;
+    }
+    set_task_priority(&task, priority);
+    0
+}
+
+/// The priority of task pid, 0 for the caller itself, not counting the
+/// priority lent by the callers it serves.
+pub fn sys_get_priority(pid: usize) -> isize {
+    match target_task(pid) {
+        Some(task) => task.acquire_inner_lock().priority as isize,
+        None => ENOENT,
+    }
+}
+
+/// Make the caller a periodic task whose jobs are released every period_ms,
+/// the first one right away. A job should run for at most budget_ms and complete
+/// within deadline_ms of its release, 0 for the end of the period. A period of 0
+/// makes the caller an ordinary task again.
+pub fn sys_set_periodic(period_ms: usize, budget_ms: usize, deadline_ms: usize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.acquire_inner_lock();
+    if period_ms == 0 {
+        inner.periodic = None;
+        return 0;
+    }
+    let deadline_ms = if deadline_ms == 0 { period_ms } else { deadline_ms };
+    if budget_ms == 0 || budget_ms > deadline_ms || deadline_ms > period_ms {
+        return EINVAL;
+    }
+    inner.periodic = Some(Periodic::new(period_ms, budget_ms, deadline_ms, get_time_ms()));
+    0
+}
+
+/// Complete the current job of the caller and block until the next one is released.
+pub fn sys_wait_period() -> isize {
+    let task = current_task().unwrap();
+    let now = get_time_ms();
+    let release = match task.acquire_inner_lock().periodic.as_mut() {
+        Some(periodic) => periodic.complete(now),
+        None => return EINVAL,
+    };
+    drop(task);
+    sleep_current_until(release);
+    0
+}
+
+/// Copy the counters of the periodic task pid, 0 for the caller, to stats.
+pub fn sys_periodic_stats(pid: usize, stats: *mut PeriodicStats) -> isize {
+    let task = match target_task(pid) {
+        Some(task) => task,
+        None => return ENOENT,
+    };
+    let counters = match task.acquire_inner_lock().periodic.as_ref() {
+        Some(periodic) => periodic.stats,
+        None => return EINVAL,
+    };
+    *translated_refmut(current_user_token(), stats) = counters;
+    0
+}
+
 pub fn sys_create_task(file: *const u8) -> isize {
     let token = current_user_token();
     let path = translated_str(token, file);
     if let Some(data) = get_app_data_by_name(path.as_str()) {
         let task = current_task().unwrap();
-        let next = task.create(data);
+        let next = task.create(path.as_str(), data);
         let pid = next.pid.0 as isize;
         add_task(next);
         pid
@@ -169,13 +295,82 @@ pub fn sys_create_task(file: *const u8) -> isize {
     }
 }
 
-pub fn sys_register(file: *const u8, serivce: *const u8) -> isize {
+pub const RESTART_NEVER: usize = 0;
+pub const RESTART_ON_FAILURE: usize = 1;
+pub const RESTART_ALWAYS: usize = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RestartArgs {
+    mode: usize,
+    max_retries: usize,
+    backoff_ms: usize,
+}
+
+/// Start file and register it as service, a non-null restart selects what
+/// happens once the task exits; without one the name is simply dropped.
+/// Return EEXIST if a topic, a pool or a live task holds the name. With the
+/// user-space name server running, the name is registered with it as well and
+/// its refusal returned, the task is killed then.
+pub fn sys_register(file: *const u8, serivce: *const u8, restart: *const RestartArgs) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, serivce));
+    // reject a malformed name before the task is created
+    if service.segments().is_none() {
+        return -1;
+    }
+    let policy = if restart.is_null() {
+        RestartPolicy::default()
+    } else {
+        let args = *translated_refmut(token, restart as *mut RestartArgs);
+        let mode = match args.mode {
+            RESTART_NEVER => RestartMode::Never,
+            RESTART_ON_FAILURE => RestartMode::OnFailure,
+            RESTART_ALWAYS => RestartMode::Always,
+            _ => return -1,
+        };
+        RestartPolicy {
+            mode,
+            max_retries: args.max_retries,
+            backoff_ms: args.backoff_ms,
+        }
+    };
+    if let Err(err) = REGISTRY.check_free(&service) {
+        return err;
+    }
     let pid = sys_create_task(file);
     if pid == -1 {
         return -1
     }
-    let token = current_user_token();
-    let service_path = translated_str(token, serivce);
-    REGISTRY.register(&PidHandle(pid as usize), &Service::new(service_path));
+    // nothing can take the name while the task is created
+    REGISTRY.register(pid as usize, &service).unwrap();
+    REGISTRY.set_restart(&service, translated_str(token, file), policy, DEFAULT_PRIORITY);
+    // names are looked up through the name server, which may have given this
+    // one to a task registered with it directly
+    #[cfg(feature = "user-nameserver")]
+    if let Some(status) = crate::service::nameserver_register(pid as usize, &service.path) {
+        if status < 0 {
+            REGISTRY.unpublish(pid as usize, &service).ok();
+            crate::task::kill_task(pid as usize);
+            return status;
+        }
+    }
     pid
-} 
+}
+
+/// Copy the name of the time partition of task pid, 0 for the caller, to buf and
+/// return its length. Return ENOENT if there is no such task or it runs in no
+/// partition, as every task does while time is not partitioned.
+pub fn sys_task_partition(pid: usize, buf: *mut u8, len: usize) -> isize {
+    let name = match target_task(pid).as_ref().and_then(task_partition) {
+        Some(name) => name,
+        None => return ENOENT,
+    };
+    let token = current_user_token();
+    let mut copied = 0;
+    for bytes in translated_byte_buffer(token, buf, len.min(name.len())) {
+        bytes.copy_from_slice(&name.as_bytes()[copied..copied + bytes.len()]);
+        copied += bytes.len();
+    }
+    name.len() as isize
+}
@@ -0,0 +1,257 @@
+use alloc::vec::Vec;
+
+use crate::errno::{EACCES, EBADF, EINVAL, ENOENT, ETIMEDOUT};
+use crate::ipc::Rights;
+use crate::mm::{translated_byte_buffer, translated_str};
+use crate::service::{may_send, Dispatch, REGISTRY, Service, WatchdogAction};
+use crate::task::{current_task, current_user_token, find_task};
+use crate::timer::deadline_after;
+#[cfg(feature = "user-nameserver")]
+use crate::service::nameserver_lookup;
+#[cfg(feature = "user-nameserver")]
+use crate::task::{current_killed, sleep_current_until};
+#[cfg(feature = "user-nameserver")]
+use kernel_hal::timer::get_time_ms;
+
+/// List every registered service at or below the path instead of its direct children.
+const SERVICE_LIST_RECURSIVE: usize = 1 << 0;
+
+/// Publish the mailbox of the task instead of one of its channels.
+const SERVICE_PUBLISH_MAILBOX: usize = usize::MAX;
+
+/// How often service_wait asks the name server again.
+#[cfg(feature = "user-nameserver")]
+const NAMESERVER_POLL_MS: usize = 10;
+
+/// Bind the calling task to path. Clients connecting to path get a write handle
+/// to the channel behind handle, which must carry WRITE and DUPLICATE, or to the
+/// mailbox of the task with SERVICE_PUBLISH_MAILBOX. Return EEXIST if another
+/// live task or a topic holds the name.
+pub fn sys_service_publish(path: *const u8, handle: usize) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let task = current_task().unwrap();
+    let endpoint = if handle == SERVICE_PUBLISH_MAILBOX {
+        None
+    } else {
+        let inner = task.acquire_inner_lock();
+        let endpoint = inner
+            .get_handle(handle)
+            .ok_or(EBADF)
+            .and_then(|handle| handle.channel(Rights::WRITE | Rights::DUPLICATE));
+        match endpoint {
+            Ok(endpoint) => Some(endpoint),
+            Err(err) => return err,
+        }
+    };
+    match REGISTRY.publish(task.getpid(), &service, endpoint) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// Remove the binding of path made by the calling task. Return ENOENT if nothing
+/// is bound to path and EACCES if another task is.
+pub fn sys_service_unpublish(path: *const u8) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let pid = current_task().unwrap().getpid();
+    match REGISTRY.unpublish(pid, &service) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// Remove path and every name below it. Return ENOENT if path is not in the tree,
+/// EINVAL for the empty path and EACCES if another live task serves one of them.
+pub fn sys_service_remove(path: *const u8) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let pid = current_task().unwrap().getpid();
+    match REGISTRY.remove(pid, &service) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// Store the paths of the nodes directly below path in buf, each one followed by
+/// a NUL byte. The empty path lists the top level. With SERVICE_LIST_RECURSIVE,
+/// list the registered services anywhere below path, and path itself if registered.
+/// Return the length of the whole list, buf only gets the entries which fit.
+/// Return ENOENT if nothing is registered under path.
+pub fn sys_service_list(path: *const u8, buf: *mut u8, len: usize, flags: usize) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let paths = if flags & SERVICE_LIST_RECURSIVE != 0 {
+        REGISTRY.find_prefix(&service)
+    } else {
+        REGISTRY.list_children(&service)
+    };
+    let paths = match paths {
+        Some(paths) => paths,
+        None => return ENOENT,
+    };
+    let mut list: Vec<u8> = Vec::new();
+    for path in paths.iter() {
+        // whole entries only
+        if list.len() + path.len() + 1 > len {
+            break;
+        }
+        list.extend_from_slice(path.as_bytes());
+        list.push(0);
+    }
+    let mut start = 0;
+    for bytes in translated_byte_buffer(token, buf, list.len()) {
+        bytes.copy_from_slice(&list[start..start + bytes.len()]);
+        start += bytes.len();
+    }
+    paths.iter().map(|path| path.len() + 1).sum::<usize>() as isize
+}
+
+/// Return the pid of the task registered as path, ENOENT if there is none,
+/// EINVAL if path has an empty segment. The user-space name server answers
+/// instead of the registry while it runs.
+pub fn sys_service_lookup(path: *const u8) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    if service.segments().is_none() {
+        return EINVAL;
+    }
+    #[cfg(feature = "user-nameserver")]
+    if let Some(result) = nameserver_lookup(&service.path) {
+        return result.map_or_else(|err| err, |(pid, _)| pid as isize);
+    }
+    match REGISTRY.find_task(&service) {
+        Some(pid) => pid as isize,
+        None => ENOENT,
+    }
+}
+
+// Look path up through the name server until it is served or deadline_ms
+// passes, None without a name server. Names registered with it directly do
+// not wake the registry up, so it is asked again every NAMESERVER_POLL_MS.
+#[cfg(feature = "user-nameserver")]
+fn nameserver_wait(path: &str, deadline_ms: usize) -> Option<isize> {
+    loop {
+        match nameserver_lookup(path)? {
+            Ok((pid, _)) => return Some(pid as isize),
+            Err(ENOENT) => {}
+            Err(err) => return Some(err),
+        }
+        let now = get_time_ms();
+        if now >= deadline_ms || current_killed() {
+            return Some(ETIMEDOUT);
+        }
+        sleep_current_until(deadline_ms.min(now + NAMESERVER_POLL_MS));
+    }
+}
+
+/// Block until a task is registered as path and return its pid,
+/// or ETIMEDOUT once timeout_ms passes. usize::MAX waits forever.
+/// Like service_lookup, asks the user-space name server while it runs.
+pub fn sys_service_wait(path: *const u8, timeout_ms: usize) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    if service.segments().is_none() {
+        return EINVAL;
+    }
+    #[cfg(feature = "user-nameserver")]
+    if let Some(result) = nameserver_wait(&service.path, deadline_after(timeout_ms)) {
+        return result;
+    }
+    match REGISTRY.wait_for(&service, deadline_after(timeout_ms)) {
+        Some(pid) => pid as isize,
+        None => ETIMEDOUT,
+    }
+}
+
+/// Query the access policy: return 0 if task pid may send to the service at path,
+/// EACCES if it may not and ENOENT if there is no such task.
+pub fn sys_access_check(pid: usize, path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let app = match find_task(pid) {
+        Some(task) => task.acquire_inner_lock().name.clone(),
+        None => return ENOENT,
+    };
+    if may_send(pid, &app, &path) {
+        0
+    } else {
+        EACCES
+    }
+}
+
+const WATCHDOG_LOG: usize = 0;
+const WATCHDOG_RESTART: usize = 1;
+const WATCHDOG_NOTIFY: usize = 2;
+
+/// Watch the service at path, which the calling task must be registered as: unless
+/// the task calls heartbeat at least every interval_ms, the kernel logs it, kills
+/// and restarts the service or, with WATCHDOG_NOTIFY, writes the path to the mailbox
+/// of the supervisor service. An interval of 0 stops watching.
+pub fn sys_service_watchdog(
+    path: *const u8,
+    interval_ms: usize,
+    action: usize,
+    supervisor: *const u8,
+) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let action = match action {
+        WATCHDOG_LOG => WatchdogAction::Log,
+        WATCHDOG_RESTART => WatchdogAction::Restart,
+        WATCHDOG_NOTIFY if !supervisor.is_null() => {
+            WatchdogAction::Notify(translated_str(token, supervisor))
+        }
+        _ => return EINVAL,
+    };
+    let pid = current_task().unwrap().getpid();
+    match REGISTRY.set_watchdog(pid, &service, interval_ms, action) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// Tell the watchdogs of the services of the calling task that it is alive.
+/// Return ENOENT if none of them is watched.
+pub fn sys_heartbeat() -> isize {
+    let pid = current_task().unwrap().getpid();
+    if REGISTRY.heartbeat(pid) {
+        0
+    } else {
+        ENOENT
+    }
+}
+
+const DISPATCH_ROUND_ROBIN: usize = 0;
+const DISPATCH_LEAST_QUEUED: usize = 1;
+
+/// Add the calling task to the pool of instances serving path. Messages written
+/// through handles connected to path are spread over the instances round-robin,
+/// or to the one with the fewest queued messages, as chosen by the first instance.
+/// Return EEXIST if a single task is registered under path.
+pub fn sys_service_join(path: *const u8, dispatch: usize) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let dispatch = match dispatch {
+        DISPATCH_ROUND_ROBIN => Dispatch::RoundRobin,
+        DISPATCH_LEAST_QUEUED => Dispatch::LeastQueued,
+        _ => return EINVAL,
+    };
+    let pid = current_task().unwrap().getpid();
+    match REGISTRY.join(pid, &service, dispatch) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// Remove the calling task from the pool serving path, ENOENT if it is not in it.
+pub fn sys_service_leave(path: *const u8) -> isize {
+    let token = current_user_token();
+    let service = Service::new(translated_str(token, path));
+    let pid = current_task().unwrap().getpid();
+    match REGISTRY.leave(pid, &service) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
@@ -1,49 +1,61 @@
+use super::processor::set_need_resched;
+use super::scheduler::{default_scheduler, Scheduler};
 use super::task::TaskControlBlock;
-use alloc::{collections::VecDeque, sync::Arc};
+use alloc::{boxed::Box, collections::BTreeMap, sync::{Arc, Weak}};
 use lazy_static::*;
 use spin::Mutex;
 
-pub struct TaskManager {
-    pub ready_queue: VecDeque<Arc<TaskControlBlock>>,
+lazy_static! {
+    pub static ref TASK_MANAGER: Mutex<Box<dyn Scheduler>> = Mutex::new(default_scheduler());
+    // every live task, including the running and blocked ones which are not in the ready queue
+    static ref PID2TCB: Mutex<BTreeMap<usize, Weak<TaskControlBlock>>> = Mutex::new(BTreeMap::new());
 }
 
-impl TaskManager {
-    pub fn new() -> Self {
-        Self {
-            ready_queue: VecDeque::new(),
-        }
-    }
+pub fn add_task(task: Arc<TaskControlBlock>) {
+    TASK_MANAGER.lock().add(task);
+}
 
-    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
-    }
+pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
+    TASK_MANAGER.lock().fetch()
+}
 
-    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
-    }
+pub fn remove_task(pid: usize) {
+    TASK_MANAGER.lock().remove(pid);
+}
 
-    pub fn find_task(&self, pid: usize) -> Option<Arc<TaskControlBlock>> {
-        for task in self.ready_queue.iter() {
-            if task.pid.0 == pid {
-                return Some(Arc::clone(task));
-            }
-        }
-        None
-    }
+// Whether the running task should give way after a timer tick
+pub fn tick_task(current: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGER.lock().on_tick(current)
 }
 
-lazy_static! {
-    pub static ref TASK_MANAGER: Mutex<TaskManager> = Mutex::new(TaskManager::new());
+pub fn should_preempt(current: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGER.lock().should_preempt(current)
 }
 
-pub fn add_task(task: Arc<TaskControlBlock>) {
-    TASK_MANAGER.lock().add(task);
+pub fn set_task_priority(task: &Arc<TaskControlBlock>, priority: usize) {
+    task.acquire_inner_lock().priority = priority;
+    TASK_MANAGER.lock().set_priority(task, priority);
+    // a ready task may now outrank the running one
+    set_need_resched();
 }
 
-pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.lock().fetch()
+// The time partition task runs in, None if it is in none
+pub fn task_partition(task: &Arc<TaskControlBlock>) -> Option<&'static str> {
+    TASK_MANAGER.lock().partition_of(task)
+}
+
+pub fn init_scheduler() {
+    println!("[kernel] scheduler: {}", TASK_MANAGER.lock().name());
 }
 
 pub fn find_task(pid: usize) -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.lock().find_task(pid)
+    PID2TCB.lock().get(&pid).and_then(|task| task.upgrade())
+}
+
+pub fn insert_into_pid2task(task: &Arc<TaskControlBlock>) {
+    PID2TCB.lock().insert(task.getpid(), Arc::downgrade(task));
+}
+
+pub fn remove_from_pid2task(pid: usize) {
+    PID2TCB.lock().remove(&pid);
 }
@@ -1,34 +1,75 @@
 mod context;
 mod kernel_stack;
 mod manager;
+mod periodic;
 mod pid;
 mod processor;
+mod scheduler;
 mod switch;
 mod task;
+mod wait_queue;
 
 use alloc::sync::Arc;
-use kernel_hal::{VirtAddr, VirtPageNum};
+use kernel_hal::{timer::get_time_ms, VirtAddr, VirtPageNum};
 use lazy_static::*;
-use task::{TaskControlBlock, TaskStatus};
+use task::TaskStatus;
 use crate::mm::MapPermission;
 
 pub use context::TaskContext;
 pub use kernel_stack::KernelStack;
-pub use manager::{add_task, TASK_MANAGER, find_task};
+pub use manager::{
+    add_task, init_scheduler, set_task_priority, task_partition, TASK_MANAGER, find_task,
+};
+use manager::{insert_into_pid2task, remove_from_pid2task, remove_task, should_preempt, tick_task};
+pub use periodic::{Periodic, PeriodicStats};
 pub use pid::{pid_alloc, PidHandle};
 pub use processor::{
     current_task, current_trap_cx, current_user_token, run_tasks, schedule, take_current_task,
 };
+use processor::{set_need_resched, take_need_resched};
+pub use task::{TaskControlBlock, DEFAULT_PRIORITY, MAX_PRIORITY};
+pub use wait_queue::WaitQueue;
 
 use crate::loader::get_app_data_by_name;
+use crate::service::REGISTRY;
 
 lazy_static! {
     pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new(TaskControlBlock::new(
+        "initproc",
         get_app_data_by_name("initproc").unwrap()
     ));
 }
 
+/// Exit code of a task ended by kill_task.
+pub const EXIT_KILLED: i32 = -9;
+
+// Make task pid exit the next time it returns from a trap. A blocked task is woken up,
+// its wait returns early. Return false if there is no such task.
+pub fn kill_task(pid: usize) -> bool {
+    match find_task(pid) {
+        Some(task) => {
+            task.acquire_inner_lock().killed = true;
+            wakeup_task(task);
+            true
+        }
+        None => false,
+    }
+}
+
+// Whether the current task was killed, blocking calls give up then
+pub fn current_killed() -> bool {
+    current_task().unwrap().acquire_inner_lock().killed
+}
+
+// Called on the way back to user space
+pub fn exit_current_if_killed() {
+    if current_killed() {
+        exit_current_and_run_next(EXIT_KILLED);
+    }
+}
+
 pub fn add_initproc() {
+    insert_into_pid2task(&INITPROC);
     add_task(INITPROC.clone());
 }
 
@@ -50,11 +91,85 @@ pub fn suspend_current_and_run_next() {
     schedule(task_cx_ptr2);
 }
 
+// Called on every timer interrupt, the scheduler decides whether the running task goes on
+pub fn tick_current_and_run_next() {
+    let task = current_task().unwrap();
+    if let Some(periodic) = task.acquire_inner_lock().periodic.as_mut() {
+        periodic.charge(get_time_ms());
+    }
+    let preempt = tick_task(&task);
+    drop(task);
+    if preempt {
+        suspend_current_and_run_next();
+    }
+}
+
+// Called on the way back to user space, give way to a task which became ready
+// meanwhile if the scheduler ranks it above the current one
+pub fn preempt_current_if_needed() {
+    if !take_need_resched() {
+        return;
+    }
+    let task = current_task().unwrap();
+    let preempt = should_preempt(&task);
+    drop(task);
+    if preempt {
+        suspend_current_and_run_next();
+    }
+}
+
+// Block the current task until get_time_ms() reaches deadline_ms
+pub fn sleep_current_until(deadline_ms: usize) {
+    if get_time_ms() < deadline_ms {
+        // nothing wakes the queue up but the deadline
+        WaitQueue::new().wait_until(deadline_ms);
+    }
+}
+
+pub fn block_current_and_run_next() {
+    // There must be an application running.
+    let task = take_current_task().unwrap();
+
+    // ---- hold current PCB lock
+    let mut task_inner = task.acquire_inner_lock();
+    let task_cx_ptr2 = task_inner.get_task_cx_ptr2();
+    // Change status to Blocked, the wait queue keeps the task alive
+    task_inner.task_status = TaskStatus::Blocked;
+    drop(task_inner);
+    // ---- release current PCB lock
+
+    drop(task);
+    // jump to scheduling cycle
+    schedule(task_cx_ptr2);
+}
+
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    // ---- hold task PCB lock
+    let mut task_inner = task.acquire_inner_lock();
+    if task_inner.task_status != TaskStatus::Blocked {
+        return;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    drop(task_inner);
+    // ---- release task PCB lock
+
+    // push back to ready queue.
+    add_task(task);
+    // the running task, if any, is checked for preemption before it returns to user space
+    set_need_resched();
+}
+
 pub fn exit_current_and_run_next(exit_code: i32) {
     // take from Processor
     let task = take_current_task().unwrap();
+    remove_from_pid2task(task.getpid());
+    remove_task(task.getpid());
+    // forget or restart the services backed by this task
+    REGISTRY.task_exited(task.getpid(), exit_code);
     // **** hold current PCB lock
     let mut inner = task.acquire_inner_lock();
+    let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+    let adopted = !inner.children.is_empty();
     // Change status to Zombie
     inner.task_status = TaskStatus::Zombie;
     // Record exit code
@@ -74,8 +189,19 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     inner.children.clear();
     // deallocate user space
     inner.memory_set.recycle_data_pages();
+    // release the kernel objects held by handles once the lock is gone,
+    // closing a channel wakes up the tasks waiting on its peer
+    let handle_table = core::mem::take(&mut inner.handle_table);
     drop(inner);
     // **** release current PCB lock
+    drop(handle_table);
+    // a parent blocked in waitpid can reap us, and initproc the zombies among our children
+    if let Some(parent) = parent {
+        parent.child_exited.wake_all();
+    }
+    if adopted {
+        INITPROC.child_exited.wake_all();
+    }
     // drop task manually to maintain rc correctly
     drop(task);
     // we do not have to save task context
@@ -0,0 +1,86 @@
+/// Jobs and timing failures of a periodic task.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct PeriodicStats {
+    // completed jobs
+    pub jobs: usize,
+    // jobs completed after their deadline or skipped because it passed before their release
+    pub deadline_misses: usize,
+    // jobs which ran for longer than the budget
+    pub budget_overruns: usize,
+}
+
+/// Timing of a periodic task: a job is released every period_ms, should run
+/// for at most budget_ms and complete within deadline_ms of its release.
+pub struct Periodic {
+    pub period_ms: usize,
+    pub budget_ms: usize,
+    pub deadline_ms: usize,
+    // release and absolute deadline of the current job
+    pub release_ms: usize,
+    pub abs_deadline_ms: usize,
+    // processor time used by the current job, charged at timer ticks and when it completes
+    used_ms: usize,
+    charged_at: usize,
+    // the current job is already counted as a miss or an overrun
+    missed: bool,
+    overran: bool,
+    pub stats: PeriodicStats,
+}
+
+impl Periodic {
+    // The first job is released at now_ms
+    pub fn new(period_ms: usize, budget_ms: usize, deadline_ms: usize, now_ms: usize) -> Self {
+        Self {
+            period_ms,
+            budget_ms,
+            deadline_ms,
+            release_ms: now_ms,
+            abs_deadline_ms: now_ms + deadline_ms,
+            used_ms: 0,
+            charged_at: now_ms,
+            missed: false,
+            overran: false,
+            stats: PeriodicStats::default(),
+        }
+    }
+
+    // The task is switched in at now_ms, the time it was switched out is not charged
+    pub fn resume(&mut self, now_ms: usize) {
+        self.charged_at = now_ms;
+    }
+
+    // Charge the current job with the time it ran since the last charge
+    pub fn charge(&mut self, now_ms: usize) {
+        self.used_ms += now_ms.saturating_sub(self.charged_at);
+        self.charged_at = now_ms;
+        if self.used_ms > self.budget_ms && !self.overran {
+            self.overran = true;
+            self.stats.budget_overruns += 1;
+        }
+        if now_ms > self.abs_deadline_ms && !self.missed {
+            self.missed = true;
+            self.stats.deadline_misses += 1;
+        }
+    }
+
+    // The current job is done at now_ms, release the next one and return its release time
+    pub fn complete(&mut self, now_ms: usize) -> usize {
+        self.charge(now_ms);
+        self.stats.jobs += 1;
+        let mut release = self.release_ms + self.period_ms;
+        // jobs whose deadline passes before they could start are skipped
+        if release + self.deadline_ms <= now_ms {
+            let skipped = (now_ms - release - self.deadline_ms) / self.period_ms + 1;
+            self.stats.deadline_misses += skipped;
+            release += skipped * self.period_ms;
+        }
+        self.release_ms = release;
+        self.abs_deadline_ms = release + self.deadline_ms;
+        self.used_ms = 0;
+        self.charged_at = release.max(now_ms);
+        self.missed = false;
+        self.overran = false;
+        release
+    }
+}
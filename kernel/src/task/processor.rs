@@ -1,3 +1,5 @@
+use crate::timer::check_timer;
+use kernel_hal::timer::get_time_ms;
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
 use core::cell::RefCell;
@@ -18,6 +20,8 @@ unsafe impl Sync for Processor {}
 struct ProcessorInner {
     current: Option<Arc<TaskControlBlock>>,
     idle_task_cx_ptr: usize,
+    // a task became ready since the current one was switched in
+    need_resched: bool,
 }
 
 impl Processor {
@@ -26,6 +30,7 @@ impl Processor {
             inner: RefCell::new(ProcessorInner {
                 current: None,
                 idle_task_cx_ptr: 0,
+                need_resched: false,
             }),
         }
     }
@@ -42,6 +47,14 @@ impl Processor {
             .map(|task| Arc::clone(task))
     }
 
+    pub fn set_need_resched(&self) {
+        self.inner.borrow_mut().need_resched = true;
+    }
+
+    pub fn take_need_resched(&self) -> bool {
+        core::mem::replace(&mut self.inner.borrow_mut().need_resched, false)
+    }
+
     fn get_idle_task_cx_ptr2(&self) -> *const usize {
         let inner = self.inner.borrow();
         &inner.idle_task_cx_ptr as *const usize
@@ -55,12 +68,21 @@ impl Processor {
                 let mut task_inner = task.acquire_inner_lock();
                 let next_task_cx_ptr2 = task_inner.get_task_cx_ptr2();
                 task_inner.task_status = TaskStatus::Running;
+                if let Some(periodic) = task_inner.periodic.as_mut() {
+                    periodic.resume(get_time_ms());
+                }
                 // release
                 drop(task_inner);
-                self.inner.borrow_mut().current = Some(task);
+                let mut inner = self.inner.borrow_mut();
+                inner.current = Some(task);
+                inner.need_resched = false;
+                drop(inner);
                 unsafe {
                     __switch(idle_task_cx_ptr2, next_task_cx_ptr2);
                 }
+            } else {
+                // every task is blocked, timer interrupts are off in the kernel so poll the timers
+                check_timer();
             }
         }
     }
@@ -82,6 +104,14 @@ pub fn current_task() -> Option<Arc<TaskControlBlock>> {
     PROCESSOR.current()
 }
 
+pub fn set_need_resched() {
+    PROCESSOR.set_need_resched();
+}
+
+pub fn take_need_resched() -> bool {
+    PROCESSOR.take_need_resched()
+}
+
 pub fn current_user_token() -> usize {
     let task = current_task().unwrap();
     let token = task.acquire_inner_lock().get_user_token();
@@ -0,0 +1,71 @@
+mod partition;
+mod priority;
+mod real_time;
+mod round_robin;
+mod stride;
+
+use alloc::{boxed::Box, sync::Arc};
+use crate::loader::get_partition_schedule;
+use super::task::TaskControlBlock;
+
+pub use partition::{PartitionSchedule, PartitionScheduler};
+pub use priority::PriorityScheduler;
+pub use real_time::{RealTimePolicy, RealTimeScheduler};
+pub use round_robin::RoundRobinScheduler;
+pub use stride::StrideScheduler;
+
+#[cfg(all(feature = "sched-round-robin", feature = "sched-stride"))]
+compile_error!("the sched-round-robin and sched-stride features are mutually exclusive");
+
+/// A scheduling policy: decides which ready task runs next and when the
+/// running task gives way to another one.
+pub trait Scheduler: Send {
+    fn name(&self) -> &'static str;
+    /// Make task ready to run.
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    /// Take the ready task to run next.
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// Forget the exited task pid, taking it out of the ready tasks if it is one of them.
+    fn remove(&mut self, pid: usize) -> Option<Arc<TaskControlBlock>>;
+    /// Account a timer tick to the running task, return true to preempt it.
+    fn on_tick(&mut self, current: &Arc<TaskControlBlock>) -> bool;
+    /// Whether a ready task should take the processor from current right away,
+    /// asked on the way back to user space after tasks became ready.
+    fn should_preempt(&self, _current: &Arc<TaskControlBlock>) -> bool {
+        false
+    }
+    /// Called after the priority of task changed, which may be a ready task.
+    fn set_priority(&mut self, _task: &Arc<TaskControlBlock>, _priority: usize) {}
+    /// The time partition task runs in, None if time is not partitioned.
+    fn partition_of(&self, _task: &Arc<TaskControlBlock>) -> Option<&'static str> {
+        None
+    }
+}
+
+// The policy of the tasks, within each partition if time is partitioned: periodic tasks
+// under EDF, or rate-monotonic with sched-rm, then the other tasks under fixed-priority
+// unless a sched-* feature picks another
+fn task_scheduler() -> Box<dyn Scheduler> {
+    let others: Box<dyn Scheduler> = if cfg!(feature = "sched-round-robin") {
+        Box::new(RoundRobinScheduler::new())
+    } else if cfg!(feature = "sched-stride") {
+        Box::new(StrideScheduler::new())
+    } else {
+        Box::new(PriorityScheduler::new())
+    };
+    let policy = if cfg!(feature = "sched-rm") {
+        RealTimePolicy::RateMonotonic
+    } else {
+        RealTimePolicy::EarliestDeadline
+    };
+    Box::new(RealTimeScheduler::new(policy, others))
+}
+
+// The policy the kernel is built with, partitioned in time when the partition schedule
+// embedded by build.rs has windows
+pub fn default_scheduler() -> Box<dyn Scheduler> {
+    match PartitionSchedule::parse(get_partition_schedule()) {
+        Some(schedule) => Box::new(PartitionScheduler::new(schedule, task_scheduler)),
+        None => task_scheduler(),
+    }
+}
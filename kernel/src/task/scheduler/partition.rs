@@ -0,0 +1,215 @@
+use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
+use kernel_hal::timer::get_time_ms;
+use super::super::task::TaskControlBlock;
+use super::Scheduler;
+
+/// What happens to the rest of a window once its partition has no ready task.
+#[derive(Clone, Copy)]
+pub enum UnusedTime {
+    Idle,
+    // the tasks of the other partitions run until the window ends
+    Donate,
+}
+
+struct Window {
+    // index of the partition, or the number of partitions for the tasks in none
+    slot: usize,
+    duration_ms: usize,
+}
+
+/// A static schedule of time partitions, each a group of tasks named by the
+/// apps they run. The major frame is the sequence of windows, repeated forever.
+pub struct PartitionSchedule {
+    partitions: Vec<(&'static str, Vec<&'static str>)>,
+    windows: Vec<Window>,
+    unused: UnusedTime,
+}
+
+impl PartitionSchedule {
+    /// Parse `partition <name> <apps>`, `window <name> <duration_ms>` and
+    /// `unused idle|donate` lines. None without windows, leaving time unpartitioned,
+    /// and when the tasks in no partition would never run.
+    pub fn parse(schedule: &'static str) -> Option<Self> {
+        let mut partitions: Vec<(&'static str, Vec<&'static str>)> = Vec::new();
+        let mut windows: Vec<(&'static str, usize)> = Vec::new();
+        let mut unused = UnusedTime::Idle;
+        for line in schedule.lines() {
+            let fields: Vec<&'static str> = line.split(' ').collect();
+            match fields[..] {
+                ["partition", "-", _] => {
+                    println!("[kernel] partition - is reserved for tasks in no partition");
+                }
+                ["partition", name, apps] => partitions.push((name, apps.split(',').collect())),
+                ["window", name, duration] => match duration.parse() {
+                    Ok(duration_ms) if duration_ms > 0 => windows.push((name, duration_ms)),
+                    _ => println!("[kernel] malformed partition schedule line: {}", line),
+                },
+                ["unused", "idle"] => unused = UnusedTime::Idle,
+                ["unused", "donate"] => unused = UnusedTime::Donate,
+                _ => println!("[kernel] malformed partition schedule line: {}", line),
+            }
+        }
+        let mut resolved = Vec::new();
+        for (name, duration_ms) in windows {
+            let slot = if name == "-" {
+                Some(partitions.len())
+            } else {
+                partitions.iter().position(|(partition, _)| *partition == name)
+            };
+            match slot {
+                Some(slot) => resolved.push(Window { slot, duration_ms }),
+                None => println!("[kernel] window for unknown partition {} skipped", name),
+            }
+        }
+        if resolved.is_empty() {
+            return None;
+        }
+        let unpartitioned = resolved.iter().any(|window| window.slot == partitions.len());
+        if let (UnusedTime::Idle, false) = (unused, unpartitioned) {
+            // initproc, the shell and the services are in no partition
+            println!("[kernel] partition schedule ignored, it needs a window for - or donation");
+            return None;
+        }
+        Some(Self { partitions, windows: resolved, unused })
+    }
+
+    fn major_frame_ms(&self) -> usize {
+        self.windows.iter().map(|window| window.duration_ms).sum()
+    }
+}
+
+/// Only the tasks of the partition of the current window run, each partition
+/// under its own scheduler. Windows are switched by the timer ticks and by the
+/// idle loop.
+pub struct PartitionScheduler {
+    schedule: PartitionSchedule,
+    // one per partition, then one for the tasks in no partition
+    slots: Vec<Box<dyn Scheduler>>,
+    // number of ready tasks in each slot
+    ready: Vec<usize>,
+    window: usize,
+    window_end_ms: usize,
+}
+
+impl PartitionScheduler {
+    pub fn new(schedule: PartitionSchedule, scheduler: fn() -> Box<dyn Scheduler>) -> Self {
+        println!(
+            "[kernel] {} partitions, {} windows in a major frame of {} ms",
+            schedule.partitions.len(),
+            schedule.windows.len(),
+            schedule.major_frame_ms()
+        );
+        let slots = (0..=schedule.partitions.len()).map(|_| scheduler()).collect();
+        let window_end_ms = get_time_ms() + schedule.windows[0].duration_ms;
+        Self {
+            ready: vec![0; schedule.partitions.len() + 1],
+            schedule,
+            slots,
+            window: 0,
+            window_end_ms,
+        }
+    }
+
+    fn slot_of(&self, task: &Arc<TaskControlBlock>) -> usize {
+        let inner = task.acquire_inner_lock();
+        let app = inner.name.as_str();
+        self.schedule
+            .partitions
+            .iter()
+            .position(|(_, apps)| apps.contains(&app))
+            .unwrap_or(self.schedule.partitions.len())
+    }
+
+    fn active(&self) -> usize {
+        self.schedule.windows[self.window].slot
+    }
+
+    // Move on to the window covering now_ms, return whether the window changed
+    fn advance(&mut self, now_ms: usize) -> bool {
+        let mut switched = false;
+        while now_ms >= self.window_end_ms {
+            self.window = (self.window + 1) % self.schedule.windows.len();
+            self.window_end_ms += self.schedule.windows[self.window].duration_ms;
+            switched = true;
+        }
+        switched
+    }
+
+    fn fetch_from(&mut self, slot: usize) -> Option<Arc<TaskControlBlock>> {
+        let task = self.slots[slot].fetch()?;
+        self.ready[slot] -= 1;
+        Some(task)
+    }
+}
+
+impl Scheduler for PartitionScheduler {
+    fn name(&self) -> &'static str {
+        "partitioned"
+    }
+
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let slot = self.slot_of(&task);
+        self.slots[slot].add(task);
+        self.ready[slot] += 1;
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.advance(get_time_ms());
+        let active = self.active();
+        if let Some(task) = self.fetch_from(active) {
+            return Some(task);
+        }
+        if let UnusedTime::Donate = self.schedule.unused {
+            for slot in (0..self.slots.len()).filter(|slot| *slot != active) {
+                if let Some(task) = self.fetch_from(slot) {
+                    return Some(task);
+                }
+            }
+        }
+        None
+    }
+
+    fn remove(&mut self, pid: usize) -> Option<Arc<TaskControlBlock>> {
+        let mut removed = None;
+        for slot in 0..self.slots.len() {
+            if let Some(task) = self.slots[slot].remove(pid) {
+                self.ready[slot] -= 1;
+                removed = Some(task);
+            }
+        }
+        removed
+    }
+
+    fn on_tick(&mut self, current: &Arc<TaskControlBlock>) -> bool {
+        // the new window may belong to another partition
+        if self.advance(get_time_ms()) {
+            return true;
+        }
+        let slot = self.slot_of(current);
+        if slot == self.active() {
+            self.slots[slot].on_tick(current)
+        } else {
+            // running on donated time
+            self.ready[self.active()] > 0 || self.slots[slot].on_tick(current)
+        }
+    }
+
+    fn should_preempt(&self, current: &Arc<TaskControlBlock>) -> bool {
+        let slot = self.slot_of(current);
+        if slot == self.active() {
+            self.slots[slot].should_preempt(current)
+        } else {
+            self.ready[self.active()] > 0
+        }
+    }
+
+    fn set_priority(&mut self, task: &Arc<TaskControlBlock>, priority: usize) {
+        let slot = self.slot_of(task);
+        self.slots[slot].set_priority(task, priority);
+    }
+
+    fn partition_of(&self, task: &Arc<TaskControlBlock>) -> Option<&'static str> {
+        let slot = self.slot_of(task);
+        self.schedule.partitions.get(slot).map(|(name, _)| *name)
+    }
+}
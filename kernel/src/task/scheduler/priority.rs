@@ -0,0 +1,59 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use super::super::task::TaskControlBlock;
+use super::Scheduler;
+
+/// The ready task with the highest effective priority runs, tasks of equal
+/// priority take turns every tick.
+pub struct PriorityScheduler {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl PriorityScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+
+    // Highest effective priority among the ready tasks and the first task having it
+    fn highest(&self) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        for (i, task) in self.ready_queue.iter().enumerate() {
+            let priority = task.acquire_inner_lock().effective_priority();
+            if best.map_or(true, |(_, best_priority)| priority > best_priority) {
+                best = Some((i, priority));
+            }
+        }
+        best
+    }
+}
+
+impl Scheduler for PriorityScheduler {
+    fn name(&self) -> &'static str {
+        "fixed-priority"
+    }
+
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let (i, _) = self.highest()?;
+        self.ready_queue.remove(i)
+    }
+
+    fn remove(&mut self, pid: usize) -> Option<Arc<TaskControlBlock>> {
+        let index = self.ready_queue.iter().position(|task| task.getpid() == pid)?;
+        self.ready_queue.remove(index)
+    }
+
+    fn on_tick(&mut self, current: &Arc<TaskControlBlock>) -> bool {
+        let priority = current.acquire_inner_lock().effective_priority();
+        self.highest().map_or(false, |(_, highest)| highest >= priority)
+    }
+
+    fn should_preempt(&self, current: &Arc<TaskControlBlock>) -> bool {
+        let priority = current.acquire_inner_lock().effective_priority();
+        self.highest().map_or(false, |(_, highest)| highest > priority)
+    }
+}
@@ -0,0 +1,102 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use super::super::task::TaskControlBlock;
+use super::Scheduler;
+
+#[derive(Clone, Copy)]
+pub enum RealTimePolicy {
+    // the job with the earliest absolute deadline first
+    EarliestDeadline,
+    // the task with the shortest period first
+    RateMonotonic,
+}
+
+/// Periodic tasks with a released job run before every other task, in the
+/// order of the policy. The other tasks share what is left under the wrapped
+/// scheduler.
+pub struct RealTimeScheduler {
+    policy: RealTimePolicy,
+    ready: Vec<Arc<TaskControlBlock>>,
+    others: Box<dyn Scheduler>,
+}
+
+impl RealTimeScheduler {
+    pub fn new(policy: RealTimePolicy, others: Box<dyn Scheduler>) -> Self {
+        Self {
+            policy,
+            ready: Vec::new(),
+            others,
+        }
+    }
+
+    // Where task goes in the order of the policy, None unless it is periodic
+    fn key(&self, task: &Arc<TaskControlBlock>) -> Option<usize> {
+        let inner = task.acquire_inner_lock();
+        inner.periodic.as_ref().map(|periodic| match self.policy {
+            RealTimePolicy::EarliestDeadline => periodic.abs_deadline_ms,
+            RealTimePolicy::RateMonotonic => periodic.period_ms,
+        })
+    }
+
+    // Index and key of the first ready periodic task to run
+    fn first(&self) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        for (i, task) in self.ready.iter().enumerate() {
+            let key = self.key(task).unwrap_or(usize::MAX);
+            if best.map_or(true, |(_, best_key)| key < best_key) {
+                best = Some((i, key));
+            }
+        }
+        best
+    }
+}
+
+impl Scheduler for RealTimeScheduler {
+    fn name(&self) -> &'static str {
+        match self.policy {
+            RealTimePolicy::EarliestDeadline => "edf",
+            RealTimePolicy::RateMonotonic => "rate-monotonic",
+        }
+    }
+
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        if self.key(&task).is_some() {
+            self.ready.push(task);
+        } else {
+            self.others.add(task);
+        }
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        match self.first() {
+            Some((i, _)) => Some(self.ready.remove(i)),
+            None => self.others.fetch(),
+        }
+    }
+
+    fn remove(&mut self, pid: usize) -> Option<Arc<TaskControlBlock>> {
+        let task = self
+            .ready
+            .iter()
+            .position(|task| task.getpid() == pid)
+            .map(|i| self.ready.remove(i));
+        self.others.remove(pid).or(task)
+    }
+
+    fn on_tick(&mut self, current: &Arc<TaskControlBlock>) -> bool {
+        match self.key(current) {
+            Some(key) => self.first().map_or(false, |(_, first)| first < key),
+            None => !self.ready.is_empty() || self.others.on_tick(current),
+        }
+    }
+
+    fn should_preempt(&self, current: &Arc<TaskControlBlock>) -> bool {
+        match self.key(current) {
+            Some(key) => self.first().map_or(false, |(_, first)| first < key),
+            None => !self.ready.is_empty() || self.others.should_preempt(current),
+        }
+    }
+
+    fn set_priority(&mut self, task: &Arc<TaskControlBlock>, priority: usize) {
+        self.others.set_priority(task, priority);
+    }
+}
@@ -0,0 +1,39 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use super::super::task::TaskControlBlock;
+use super::Scheduler;
+
+/// Every ready task in turn for one tick, priorities are ignored.
+pub struct RoundRobinScheduler {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl RoundRobinScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for RoundRobinScheduler {
+    fn name(&self) -> &'static str {
+        "round-robin"
+    }
+
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready_queue.pop_front()
+    }
+
+    fn remove(&mut self, pid: usize) -> Option<Arc<TaskControlBlock>> {
+        let index = self.ready_queue.iter().position(|task| task.getpid() == pid)?;
+        self.ready_queue.remove(index)
+    }
+
+    fn on_tick(&mut self, _current: &Arc<TaskControlBlock>) -> bool {
+        !self.ready_queue.is_empty()
+    }
+}
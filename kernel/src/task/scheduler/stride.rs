@@ -0,0 +1,65 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use super::super::task::TaskControlBlock;
+use super::Scheduler;
+
+// The pass of a task grows by BIG_STRIDE / priority for every tick it runs
+const BIG_STRIDE: usize = 1 << 20;
+
+/// Stride scheduling: the task with the smallest pass runs, so each task gets
+/// a share of the processor proportional to its effective priority.
+pub struct StrideScheduler {
+    ready: Vec<Arc<TaskControlBlock>>,
+    // by pid, kept while the task runs or blocks
+    passes: BTreeMap<usize, usize>,
+}
+
+impl StrideScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready: Vec::new(),
+            passes: BTreeMap::new(),
+        }
+    }
+
+    fn pass(&self, task: &Arc<TaskControlBlock>) -> usize {
+        self.passes.get(&task.getpid()).copied().unwrap_or(0)
+    }
+
+    // Index of the ready task with the smallest pass
+    fn lowest(&self) -> Option<usize> {
+        (0..self.ready.len()).min_by_key(|i| self.pass(&self.ready[*i]))
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn name(&self) -> &'static str {
+        "stride"
+    }
+
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        // a new or long blocked task starts level with the ready ones
+        // instead of running until it catches up with them
+        let floor = self.lowest().map_or(0, |i| self.pass(&self.ready[i]));
+        let pass = self.pass(&task).max(floor);
+        self.passes.insert(task.getpid(), pass);
+        self.ready.push(task);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let i = self.lowest()?;
+        Some(self.ready.remove(i))
+    }
+
+    fn remove(&mut self, pid: usize) -> Option<Arc<TaskControlBlock>> {
+        self.passes.remove(&pid);
+        let index = self.ready.iter().position(|task| task.getpid() == pid)?;
+        Some(self.ready.remove(index))
+    }
+
+    fn on_tick(&mut self, current: &Arc<TaskControlBlock>) -> bool {
+        let priority = current.acquire_inner_lock().effective_priority().max(1);
+        let pass = self.pass(current) + BIG_STRIDE / priority;
+        self.passes.insert(current.getpid(), pass);
+        self.lowest().map_or(false, |i| self.pass(&self.ready[i]) < pass)
+    }
+}
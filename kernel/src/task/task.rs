@@ -1,7 +1,10 @@
 use crate::fs::{File, Stdin, Stdout};
-use crate::mm::{MemorySet, KERNEL_SPACE, MapPermission};
+use crate::mm::{MapArea, MemorySet, KERNEL_SPACE, MapPermission};
 use crate::trap::trap_handler;
-use crate::ipc::Channel;
+use crate::errno::ENOMEM;
+use crate::ipc::{Channel, ChannelLimits, Handle, KernelObject, Rights};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
 use alloc::vec::Vec;
@@ -15,19 +18,30 @@ use crate::{
 use super::{
     context::TaskContext,
     kernel_stack::KernelStack,
+    manager::insert_into_pid2task,
+    periodic::Periodic,
     pid::{pid_alloc, PidHandle},
+    wait_queue::WaitQueue,
 };
 
+/// Priority of a new task, a larger value runs first.
+pub const DEFAULT_PRIORITY: usize = 16;
+/// Priorities range from 1 to MAX_PRIORITY.
+pub const MAX_PRIORITY: usize = 255;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum TaskStatus {
     Ready,
     Running,
+    Blocked,
     Zombie,
 }
 
 pub struct TaskControlBlock {
     pub pid: PidHandle,
     pub kernel_stack: KernelStack,
+    // woken up whenever a child becomes a zombie
+    pub child_exited: WaitQueue,
     inner: Mutex<TaskControlBlockInner>,
 }
 
@@ -40,7 +54,7 @@ impl TaskControlBlock {
         self.pid.0
     }
 
-    pub fn new(elf_data: &[u8]) -> Self {
+    pub fn new(name: &str, elf_data: &[u8]) -> Self {
         let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
@@ -50,9 +64,11 @@ impl TaskControlBlock {
         let kernel_stack = KernelStack::new(&pid_handle);
         let kernel_stack_top = kernel_stack.get_top();
         let task_cx_ptr = kernel_stack.push_on_top(TaskContext::goto_trap_return());
+        let (mailbox, handle_table) = new_mailbox();
         let task_control_block = Self {
             pid: pid_handle,
             kernel_stack,
+            child_exited: WaitQueue::new(),
             inner: Mutex::new(TaskControlBlockInner {
                 trap_cx_ppn,
                 base_size: user_sp,
@@ -62,6 +78,7 @@ impl TaskControlBlock {
                 parent: None,
                 children: Vec::new(),
                 exit_code: 0,
+                killed: false,
                 fd_table: vec![
                     // 0 -> stdin
                     Some(Arc::new(Stdin)),
@@ -70,7 +87,12 @@ impl TaskControlBlock {
                     // 2 -> stderr
                     Some(Arc::new(Stdout)),
                 ],
-                channel: Channel::create(),
+                mailbox,
+                handle_table,
+                name: String::from(name),
+                priority: DEFAULT_PRIORITY,
+                lent_priority: BTreeMap::new(),
+                periodic: None,
             }),
         };
         // prepare TrapContext in user space
@@ -104,9 +126,20 @@ impl TaskControlBlock {
                 new_fd_table.push(None);
             }
         }
+        // the child gets its own mailbox and shares the duplicable handles of its parent
+        let (mailbox, mut new_handle_table) = new_mailbox();
+        for handle in parent_inner.handle_table.iter().skip(1) {
+            new_handle_table.push(
+                handle
+                    .as_ref()
+                    .filter(|handle| handle.rights.contains(Rights::DUPLICATE))
+                    .cloned(),
+            );
+        }
         let task_control_block = Arc::new(TaskControlBlock {
             pid: pid_handle,
             kernel_stack,
+            child_exited: WaitQueue::new(),
             inner: Mutex::new(TaskControlBlockInner {
                 task_cx_ptr: task_cx_ptr as usize,
                 task_status: TaskStatus::Ready,
@@ -116,12 +149,19 @@ impl TaskControlBlock {
                 parent: Some(Arc::downgrade(self)),
                 children: Vec::new(),
                 exit_code: 0,
+                killed: false,
                 fd_table: new_fd_table,
-                channel: Channel::create(),
+                mailbox,
+                handle_table: new_handle_table,
+                name: parent_inner.name.clone(),
+                priority: parent_inner.priority,
+                lent_priority: BTreeMap::new(),
+                periodic: None,
             }),
         });
         // add child
         parent_inner.children.push(task_control_block.clone());
+        insert_into_pid2task(&task_control_block);
         // modify kernel_sp in trap_cx
         // **** acquire child PCB lock
         let trap_cx = task_control_block.acquire_inner_lock().get_trap_cx();
@@ -132,7 +172,7 @@ impl TaskControlBlock {
         // ---- release parent PCB lock
     }
 
-    pub fn exec(&self, elf_data: &[u8]) {
+    pub fn exec(&self, name: &str, elf_data: &[u8]) {
         // memory_set with elf program headers/trampoline/trap context/user stack
         let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
         let trap_cx_ppn = memory_set
@@ -146,6 +186,7 @@ impl TaskControlBlock {
         inner.memory_set = memory_set;
         // update trap_cx ppn
         inner.trap_cx_ppn = trap_cx_ppn;
+        inner.name = String::from(name);
         // initialize trap_cx
         let trap_cx = inner.get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
@@ -158,7 +199,11 @@ impl TaskControlBlock {
         // **** release current PCB lock
     }
 
-    pub fn create(self: &Arc<TaskControlBlock>, elf_data: &[u8]) -> Arc<TaskControlBlock> {
+    pub fn create(
+        self: &Arc<TaskControlBlock>,
+        name: &str,
+        elf_data: &[u8],
+    ) -> Arc<TaskControlBlock> {
         // ---- hold parent PCB lock
         let mut parent_inner = self.acquire_inner_lock();
         // memory_set with elf program headers/trampoline/trap context/user stack
@@ -181,9 +226,12 @@ impl TaskControlBlock {
                 new_fd_table.push(None);
             }
         }
+        // a new program starts with nothing but its mailbox
+        let (mailbox, handle_table) = new_mailbox();
         let task_control_block = Arc::new(Self {
             pid: pid_handle,
             kernel_stack,
+            child_exited: WaitQueue::new(),
             inner: Mutex::new(TaskControlBlockInner {
                 trap_cx_ppn,
                 base_size: user_sp,
@@ -193,11 +241,18 @@ impl TaskControlBlock {
                 parent: Some(Arc::downgrade(self)),
                 children: Vec::new(),
                 exit_code: 0,
+                killed: false,
                 fd_table: new_fd_table,
-                channel: Channel::create(),
+                mailbox,
+                handle_table,
+                name: String::from(name),
+                priority: parent_inner.priority,
+                lent_priority: BTreeMap::new(),
+                periodic: None,
             }),
         });
         parent_inner.children.push(task_control_block.clone());
+        insert_into_pid2task(&task_control_block);
         // prepare TrapContext in user space
         let trap_cx = task_control_block.acquire_inner_lock().get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
@@ -224,11 +279,35 @@ pub struct TaskControlBlockInner {
     // 子进程
     pub children: Vec<Arc<TaskControlBlock>>,
     pub exit_code: i32,
+    // set by kill_task, the task exits the next time it leaves the kernel
+    pub killed: bool,
     pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
-    pub channel: (Arc<Channel>, Arc<Channel>), // channel0 is read endpoint, channel1 is write endpoint
+    // write endpoint of the mailbox, handed out to clients connecting to this task
+    pub mailbox: Arc<Channel>,
+    pub handle_table: Vec<Option<Handle>>,
+    // app the task runs, its identity for the access policy
+    pub name: String,
+    // inherited by the tasks it forks or creates
+    pub priority: usize,
+    // priorities lent by the callers whose requests this task is serving, by transaction id
+    pub lent_priority: BTreeMap<usize, usize>,
+    // set by set_periodic, not inherited
+    pub periodic: Option<Periodic>,
+}
+
+// Create a task mailbox, its read endpoint is installed as handle 0
+fn new_mailbox() -> (Arc<Channel>, Vec<Option<Handle>>) {
+    let (endpoint, mailbox) = Channel::create(ChannelLimits::default());
+    let handle = Handle::new(KernelObject::Channel(endpoint), Rights::READ);
+    (mailbox, vec![Some(handle)])
 }
 
 impl TaskControlBlockInner {
+    // The priority the task is scheduled with
+    pub fn effective_priority(&self) -> usize {
+        self.lent_priority.values().copied().fold(self.priority, usize::max)
+    }
+
     pub fn get_task_cx_ptr2(&self) -> *const usize {
         &self.task_cx_ptr as *const usize
     }
@@ -261,6 +340,26 @@ impl TaskControlBlockInner {
         self.memory_set.delete_framed_area(start, end);
     }
 
+    pub fn take_framed_area(&mut self, start: VirtAddr, end: VirtAddr) -> Option<MapArea> {
+        self.memory_set.take_framed_area(start, end)
+    }
+
+    // Give back an area taken by take_framed_area whose grant failed
+    pub fn restore_framed_area(&mut self, area: MapArea) {
+        self.memory_set.restore_framed_area(area);
+    }
+
+    pub fn has_free_area(&mut self, page_num: usize) -> bool {
+        self.memory_set.find_free_areas(page_num).is_some()
+    }
+
+    // Map an area granted by another task at a free address and return it,
+    // ENOMEM if there is no free range large enough
+    pub fn map_granted_area(&mut self, area: MapArea) -> Result<VirtAddr, isize> {
+        let start_vpn = self.memory_set.find_free_areas(area.page_count()).ok_or(ENOMEM)?;
+        Ok(self.memory_set.insert_moved_area(area, start_vpn))
+    }
+
     pub fn check_allocated(&self, start: VirtAddr, end: VirtAddr) -> bool {
         self.memory_set.check_allocated(start, end)
     }
@@ -278,4 +377,22 @@ impl TaskControlBlockInner {
             self.fd_table.len() - 1
         }
     }
+
+    pub fn alloc_handle(&mut self, handle: Handle) -> usize {
+        if let Some(idx) = (0..self.handle_table.len()).find(|idx| self.handle_table[*idx].is_none()) {
+            self.handle_table[idx] = Some(handle);
+            idx
+        } else {
+            self.handle_table.push(Some(handle));
+            self.handle_table.len() - 1
+        }
+    }
+
+    pub fn get_handle(&self, idx: usize) -> Option<&Handle> {
+        self.handle_table.get(idx).and_then(|handle| handle.as_ref())
+    }
+
+    pub fn remove_handle(&mut self, idx: usize) -> Option<Handle> {
+        self.handle_table.get_mut(idx).and_then(|handle| handle.take())
+    }
 }
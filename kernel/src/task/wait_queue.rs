@@ -0,0 +1,94 @@
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc};
+use kernel_hal::timer::get_time_ms;
+use spin::Mutex;
+
+use super::{
+    block_current_and_run_next, current_killed, current_task, task::TaskControlBlock, wakeup_task,
+};
+use crate::timer::{add_timer, cancel_timer};
+
+/// Tasks parked on a kernel object until it changes state.
+pub struct WaitQueue {
+    queue: Mutex<VecDeque<Arc<TaskControlBlock>>>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    // Park the current task until another task wakes it up.
+    // Return false if the task is killed, at once if it already was.
+    pub fn wait(&self) -> bool {
+        if current_killed() {
+            return false;
+        }
+        self.queue.lock().push_back(current_task().unwrap());
+        block_current_and_run_next();
+        // kill_task wakes the task without taking it off the queue
+        let task = current_task().unwrap();
+        self.queue.lock().retain(|waiter| !Arc::ptr_eq(waiter, &task));
+        !current_killed()
+    }
+
+    // Park the current task until it is woken up or get_time_ms() reaches deadline_ms.
+    // Return false if the deadline has passed or the task is killed.
+    pub fn wait_until(&self, deadline_ms: usize) -> bool {
+        if deadline_ms == usize::MAX {
+            return self.wait();
+        }
+        Self::wait_any(&[self], deadline_ms)
+    }
+
+    // Park the current task on all of queues until any of them wakes it up
+    // or get_time_ms() reaches deadline_ms. Return false if the deadline has
+    // passed or the task is killed.
+    pub fn wait_any(queues: &[&WaitQueue], deadline_ms: usize) -> bool {
+        if current_killed() {
+            return false;
+        }
+        let task = current_task().unwrap();
+        for queue in queues {
+            queue.queue.lock().push_back(task.clone());
+        }
+        let timer = if deadline_ms == usize::MAX {
+            None
+        } else {
+            let task = task.clone();
+            Some(add_timer(deadline_ms, Box::new(move || wakeup_task(task))))
+        };
+        drop(task);
+        block_current_and_run_next();
+        if let Some(timer) = timer {
+            cancel_timer(timer);
+        }
+        // still queued on everything but the queue which woke us up
+        let task = current_task().unwrap();
+        for queue in queues {
+            queue.queue.lock().retain(|waiter| !Arc::ptr_eq(waiter, &task));
+        }
+        get_time_ms() < deadline_ms && !current_killed()
+    }
+
+    pub fn wake_one(&self) {
+        let task = self.queue.lock().pop_front();
+        if let Some(task) = task {
+            wakeup_task(task);
+        }
+    }
+
+    pub fn wake_all(&self) {
+        let tasks: VecDeque<_> = self.queue.lock().drain(..).collect();
+        for task in tasks {
+            wakeup_task(task);
+        }
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
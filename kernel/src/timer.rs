@@ -0,0 +1,68 @@
+use alloc::{boxed::Box, collections::BTreeMap};
+use kernel_hal::timer::get_time_ms;
+use lazy_static::*;
+use spin::Mutex;
+
+type TimerCallback = Box<dyn FnOnce() + Send>;
+
+/// Identifies an armed timer so that it can be cancelled.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimerId {
+    expire_ms: usize,
+    seq: usize,
+}
+
+struct TimerQueue {
+    next_seq: usize,
+    // ordered by expire time, seq keeps timers with the same deadline apart
+    timers: BTreeMap<TimerId, TimerCallback>,
+}
+
+lazy_static! {
+    static ref TIMER_QUEUE: Mutex<TimerQueue> = Mutex::new(TimerQueue {
+        next_seq: 0,
+        timers: BTreeMap::new(),
+    });
+}
+
+// Run callback once get_time_ms() reaches expire_ms
+pub fn add_timer(expire_ms: usize, callback: TimerCallback) -> TimerId {
+    let mut queue = TIMER_QUEUE.lock();
+    let id = TimerId {
+        expire_ms,
+        seq: queue.next_seq,
+    };
+    queue.next_seq += 1;
+    queue.timers.insert(id, callback);
+    id
+}
+
+// Drop a timer which has not fired yet, a fired one is ignored
+pub fn cancel_timer(id: TimerId) {
+    TIMER_QUEUE.lock().timers.remove(&id);
+}
+
+// Turn a relative timeout into a deadline, usize::MAX stays infinite
+pub fn deadline_after(timeout_ms: usize) -> usize {
+    if timeout_ms == usize::MAX {
+        usize::MAX
+    } else {
+        get_time_ms().saturating_add(timeout_ms)
+    }
+}
+
+// Fire every expired timer, called on each timer interrupt and by the idle loop
+pub fn check_timer() {
+    let now = get_time_ms();
+    loop {
+        let mut queue = TIMER_QUEUE.lock();
+        let id = match queue.timers.keys().next() {
+            Some(id) if id.expire_ms <= now => *id,
+            _ => break,
+        };
+        let callback = queue.timers.remove(&id).unwrap();
+        // callbacks may arm new timers
+        drop(queue);
+        callback();
+    }
+}
@@ -9,9 +9,9 @@ use riscv::register::{
 };
 
 use crate::{syscall::syscall, task::{
-        current_trap_cx, current_user_token, exit_current_and_run_next,
-        suspend_current_and_run_next,
-    }};
+        current_trap_cx, current_user_token, exit_current_and_run_next, exit_current_if_killed,
+        preempt_current_if_needed, tick_current_and_run_next,
+    }, timer::check_timer};
 
 global_asm!(include_str!("trap.S"));
 
@@ -39,7 +39,10 @@ pub fn trap_handler() -> ! {
         Trap::Exception(Exception::UserEnvCall) => {
             let mut cx = current_trap_cx();
             cx.sepc += 4;
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            let result = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            ) as usize;
             cx = current_trap_cx();
             cx.x[10] = result;
         }
@@ -63,7 +66,8 @@ pub fn trap_handler() -> ! {
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
-            suspend_current_and_run_next();
+            check_timer();
+            tick_current_and_run_next();
         }
         _ => {
             panic!(
@@ -73,6 +77,8 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    exit_current_if_killed();
+    preempt_current_if_needed();
     trap_return()
 }
 
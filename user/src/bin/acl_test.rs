@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    access_check, channel_write, errno::EACCES, getpid, register, service_connect,
+    service_publish, service_unpublish,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid() as usize;
+    if access_check(pid, "com.sys.monitor\0") == 0 {
+        println!("no access policy guards com.sys.*, acl test skipped");
+        return 0;
+    }
+    // under the example of user/access.policy, com.sys.* only takes messages
+    // from the com.sys.* services and the shell
+    let monitor_pid = register("service_monitor\0", "com.sys.monitor\0");
+    assert!(monitor_pid > 0);
+    let monitor = service_connect("com.sys.monitor\0");
+    assert!(monitor >= 0);
+    assert_eq!(access_check(pid, "com.sys.monitor\0"), EACCES);
+    assert_eq!(channel_write(monitor as usize, b"denied"), EACCES);
+    // services no rule names are open
+    assert_eq!(access_check(pid, "com.test.sensor\0"), 0);
+    println!("access denied ok");
+
+    // the monitor was registered at run time, not started by the manifest
+    assert_eq!(access_check(monitor_pid as usize, "com.sys.monitor\0"), EACCES);
+    // a name the task gives itself grants nothing
+    assert_eq!(service_publish("com.sys.acl\0"), 0);
+    assert_eq!(access_check(pid, "com.sys.monitor\0"), EACCES);
+    assert_eq!(channel_write(monitor as usize, b"denied"), EACCES);
+    assert_eq!(service_unpublish("com.sys.acl\0"), 0);
+    println!("self-published name ignored ok");
+    println!("acl test passed!");
+    0
+}
@@ -1,24 +1,184 @@
 #![no_std]
 #![no_main]
 
-use user_lib::{channel_write, register, waitpid};
+use user_lib::{
+    channel_call, channel_create, channel_create_bounded, channel_read, channel_read_info,
+    channel_reply, channel_stats, channel_take_reply, channel_try_write, channel_write,
+    channel_write_grant, channel_write_handles,
+    errno::{EAGAIN, EINVAL, EMSGSIZE, ENOENT, EPIPE, ETIMEDOUT},
+    exit, fork, handle_close, handle_duplicate, mmap_create, object_wait_many, object_wait_one,
+    register, service_connect, service_list, service_lookup, service_wait, timer_create, timer_set,
+    topic_open, waitpid, ChannelLimits, ChannelStats, MessageInfo, WaitItem, MAX_HANDLES_PER_MSG,
+    RIGHT_TRANSFER, RIGHT_WRITE, SERVICE_LIST_RECURSIVE, SIGNAL_PEER_CLOSED, SIGNAL_READABLE,
+    SIGNAL_TIMER_FIRED, TIMEOUT_INFINITE,
+};
 
 #[macro_use]
 extern crate user_lib;
 
 #[no_mangle]
 pub fn main() -> i32 {
-    let cpid = register("service_monitor\0", "com.test.sensor");
+    let cpid = register("service_monitor\0", "com.test.sensor\0");
     if cpid == -1 {
         println!("create failed");
         return 0;
     }
+    assert_eq!(service_lookup("com.test.sensor\0"), cpid);
+    assert_eq!(service_lookup("com.test.missing\0"), ENOENT);
+    assert_eq!(service_wait("com.test.sensor\0", TIMEOUT_INFINITE), cpid);
+    assert_eq!(service_wait("com.test.missing\0", 10), ETIMEDOUT);
+    let mut names = [0u8; 32];
+    let len = service_list("com.test\0", &mut names, 0);
+    assert_eq!(&names[..len as usize], b"com.test.sensor\0");
+    let len = service_list("com\0", &mut names, SERVICE_LIST_RECURSIVE);
+    assert_eq!(&names[..len as usize], b"com.test.sensor\0");
+    let monitor = service_connect("com.test.sensor\0");
+    if monitor < 0 {
+        println!("connect failed");
+        return 0;
+    }
     let buf = ['a' as u8; 27];
-    channel_write("com.test.sensor", &buf);
+    channel_write(monitor as usize, &buf);
+
+    // hand the monitor the far end of a reply channel
+    let mut reply = [0usize; 2];
+    assert_eq!(channel_create(&mut reply), 0);
+    channel_write_handles(monitor as usize, b"reply", &reply[1..]);
+    let mut ack = [0u8; 3];
+    channel_read(reply[0], &mut ack);
+    assert_eq!(&ack, b"ack");
+    println!("monitor acked");
+
+    // synchronous request/reply
+    let mut pong = [0u8; 4];
+    let mut info = MessageInfo::default();
+    let len = channel_call(monitor as usize, b"ping", &mut pong, TIMEOUT_INFINITE, &mut info);
+    assert_eq!(&pong[..len as usize], b"pong");
+    println!("monitor replied");
+
+    // a reply too large for the buffer is kept for another try
+    let mut short = [0u8; 2];
+    let len = channel_call(monitor as usize, b"ping", &mut short, TIMEOUT_INFINITE, &mut info);
+    assert_eq!((len, info.len), (EMSGSIZE, 4));
+    let txid = info.txid;
+    let len = channel_take_reply(monitor as usize, txid, &mut short, &mut [], &mut info);
+    assert_eq!(len, EMSGSIZE);
+    assert_eq!(channel_take_reply(monitor as usize, txid, &mut pong, &mut [], &mut info), 4);
+    assert_eq!(&pong, b"pong");
+    assert_eq!(channel_take_reply(monitor as usize, txid, &mut pong, &mut [], &mut info), EINVAL);
+    println!("oversized reply kept ok");
+
+    // a reply larger than the queue of the caller is refused
+    let mut rpc = [0usize; 2];
+    let rpc_limits = ChannelLimits { max_msgs: 4, max_bytes: 8 };
+    assert_eq!(channel_create_bounded(&mut rpc, &rpc_limits), 0);
+    let pid = fork();
+    if pid == 0 {
+        let mut buf = [0u8; 4];
+        let mut info = MessageInfo::default();
+        assert_eq!(channel_read_info(rpc[1], &mut buf, 0, &mut info), 4);
+        assert_eq!(channel_reply(rpc[1], info.txid, &[0u8; 9]), EMSGSIZE);
+        assert_eq!(channel_reply(rpc[1], info.txid, b"small"), 0);
+        exit(0);
+    }
+    let mut small = [0u8; 8];
+    let len = channel_call(rpc[0], b"big?", &mut small, TIMEOUT_INFINITE, &mut info);
+    assert_eq!(&small[..len as usize], b"small");
+    waitpid(pid as usize, &mut 0);
+    println!("oversized reply refused ok");
+
+    // a private channel shared with a forked child
+    let mut handles = [0usize; 2];
+    assert_eq!(channel_create(&mut handles), 0);
+    let pid = fork();
+    if pid == 0 {
+        let mut buf = [0u8; 4];
+        let len = channel_read(handles[1], &mut buf);
+        assert_eq!(&buf[..len as usize], b"ping");
+        println!("private channel ok");
+        exit(0);
+    }
+    channel_write(handles[0], b"ping");
+    waitpid(pid as usize, &mut 0);
+
+    // closing one endpoint is observed on the other
+    let mut closed = [0usize; 2];
+    assert_eq!(channel_create(&mut closed), 0);
+    handle_close(closed[1]);
+    let mut observed = 0u32;
+    assert_eq!(object_wait_one(closed[0], SIGNAL_PEER_CLOSED, TIMEOUT_INFINITE, &mut observed), 0);
+    assert_eq!(channel_write(closed[0], b"lost"), EPIPE);
+    assert_eq!(channel_read(closed[0], &mut [0u8; 4]), EPIPE);
+    println!("peer closed ok");
+
+    // an endpoint cannot travel through its peer, nor a copy of it through itself
+    let mut looped = [0usize; 2];
+    assert_eq!(channel_create(&mut looped), 0);
+    assert_eq!(channel_write_handles(looped[0], b"loop", &looped[1..]), EINVAL);
+    let copy = handle_duplicate(looped[0], RIGHT_WRITE | RIGHT_TRANSFER) as usize;
+    assert_eq!(channel_write_handles(looped[0], b"loop", &[copy]), EINVAL);
+    let too_many = [copy; MAX_HANDLES_PER_MSG + 1];
+    assert_eq!(channel_write_handles(looped[0], b"many", &too_many), EINVAL);
+    // both handles stay with the caller, so closing one end still reaches the other
+    handle_close(looped[1]);
+    assert_eq!(object_wait_one(looped[0], SIGNAL_PEER_CLOSED, TIMEOUT_INFINITE, &mut observed), 0);
+    println!("endpoint loop rejected ok");
+
+    // a full queue pushes back on the writer
+    let mut bounded = [0usize; 2];
+    let limits = ChannelLimits { max_msgs: 2, max_bytes: 8 };
+    assert_eq!(channel_create_bounded(&mut bounded, &limits), 0);
+    assert_eq!(channel_try_write(bounded[0], b"1234"), 0);
+    assert_eq!(channel_try_write(bounded[0], b"5678"), 0);
+    assert_eq!(channel_try_write(bounded[0], b"9"), EAGAIN);
+    assert_eq!(channel_try_write(bounded[0], b"too large"), EMSGSIZE);
+    channel_read(bounded[1], &mut [0u8; 4]);
+    assert_eq!(channel_try_write(bounded[0], b"9"), 0);
+    let mut stats = ChannelStats::default();
+    channel_stats(bounded[1], &mut stats);
+    assert_eq!((stats.msgs, stats.bytes, stats.hwm_msgs, stats.hwm_bytes), (2, 5, 2, 8));
+    println!("bounded channel ok");
+
+    // wait on a channel and a timer at once
+    let mut events = [0usize; 2];
+    assert_eq!(channel_create(&mut events), 0);
+    let timer = timer_create() as usize;
+    timer_set(timer, 10);
+    let mut items = [
+        WaitItem::handle(events[1], SIGNAL_READABLE),
+        WaitItem::handle(timer, SIGNAL_TIMER_FIRED),
+    ];
+    assert_eq!(object_wait_many(&mut items, TIMEOUT_INFINITE), 1);
+    assert_eq!(items[0].observed as usize & SIGNAL_READABLE, 0);
+    assert_ne!(items[1].observed as usize & SIGNAL_TIMER_FIRED, 0);
+    channel_write(events[0], b"event");
+    assert_eq!(object_wait_many(&mut items[..1], TIMEOUT_INFINITE), 1);
+    // a topic asserts nothing and never will, waiting on it alone fails at once
+    let topic = topic_open("com.test.quiet\0") as usize;
+    assert_eq!(object_wait_one(topic, SIGNAL_READABLE, TIMEOUT_INFINITE, &mut observed), EINVAL);
+    let mut quiet = [WaitItem::handle(topic, SIGNAL_READABLE)];
+    assert_eq!(object_wait_many(&mut quiet, TIMEOUT_INFINITE), EINVAL);
+    println!("wait many ok");
+
+    // move a page to the other endpoint without copying it
+    let frame = mmap_create(4096, 0x3) as usize;
+    let page = unsafe { core::slice::from_raw_parts_mut(frame as *mut u8, 4096) };
+    page[..5].copy_from_slice(b"frame");
+    assert_eq!(channel_write_grant(events[0], b"cam", frame, 4096), 0);
+    let mut header = [0u8; 8];
+    let len = channel_read_info(events[1], &mut header, 0, &mut info);
+    assert_eq!(&header[..len as usize], b"event");
+    let len = channel_read_info(events[1], &mut header, 0, &mut info);
+    assert_eq!(&header[..len as usize], b"cam");
+    assert_eq!(info.grant_len, 4096);
+    let granted = unsafe { core::slice::from_raw_parts(info.grant_addr as *const u8, 5) };
+    assert_eq!(granted, b"frame");
+    println!("page grant ok");
+
     loop {
         if waitpid(cpid as usize, &mut 0) == -1 {
             break;
         }
     }
     0
-}
\ No newline at end of file
+}
@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    channel_call, channel_create, channel_read_info, channel_reply, exit, fork, get_priority,
+    get_time, set_priority, waitpid, MessageInfo, TIMEOUT_INFINITE,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+fn spin(duration_ms: isize) {
+    let start = get_time();
+    while get_time() < start + duration_ms {}
+}
+
+// A high priority caller, a low priority server and a medium priority task
+// spinning in between: the call completes without waiting for the spinner
+#[no_mangle]
+pub fn main() -> i32 {
+    let own = get_priority(0) as usize;
+    assert!(own > 8);
+    let mut handles = [0usize; 2];
+    assert_eq!(channel_create(&mut handles), 0);
+    let server = fork();
+    if server == 0 {
+        assert_eq!(set_priority(0, 4), 0);
+        let mut info = MessageInfo::default();
+        for _ in 0..2 {
+            let mut buf = [0u8; 4];
+            assert_eq!(channel_read_info(handles[1], &mut buf, 0, &mut info), 4);
+            channel_reply(handles[1], info.txid, &buf);
+            // busy with other work when the next request comes in
+            spin(20);
+        }
+        exit(0);
+    }
+
+    let mut reply = [0u8; 4];
+    let mut info = MessageInfo::default();
+    assert_eq!(channel_call(handles[0], b"one\0", &mut reply, TIMEOUT_INFINITE, &mut info), 4);
+    let spinner = fork();
+    if spinner == 0 {
+        assert_eq!(set_priority(0, 8), 0);
+        spin(500);
+        exit(0);
+    }
+    // the server is spinning at priority 4 behind the spinner at 8
+    let start = get_time();
+    assert_eq!(channel_call(handles[0], b"two\0", &mut reply, TIMEOUT_INFINITE, &mut info), 4);
+    let elapsed = get_time() - start;
+    assert!(elapsed < 250, "call took {} ms behind the spinner", elapsed);
+    assert_eq!(&reply, b"two\0");
+    println!("priority lent to the busy server ok");
+
+    let mut exit_code = 0;
+    assert_eq!(waitpid(server as usize, &mut exit_code), server);
+    assert_eq!(waitpid(spinner as usize, &mut exit_code), spinner);
+    println!("lend test passed!");
+    0
+}
@@ -0,0 +1,136 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, string::String, vec};
+use user_lib::{
+    channel_read_handles, channel_reply, channel_reply_handles,
+    errno::{EACCES, EEXIST, EINVAL, EMSGSIZE, ENOENT}, handle_close, handle_duplicate,
+    nameserver::{BOOTSTRAP_HANDLE, NS_LOOKUP, NS_REGISTER, NS_REQUEST_SIZE, NS_UNREGISTER},
+    object_wait_one, MessageInfo, RIGHT_DUPLICATE, RIGHT_TRANSFER, RIGHT_WRITE, SIGNAL_PEER_CLOSED,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+struct Entry {
+    handle: usize,
+    // pid of the task which registered the name
+    owner: usize,
+}
+
+// A name is free again once the task serving it has gone
+fn alive(entry: &Entry) -> bool {
+    let mut observed = 0u32;
+    object_wait_one(entry.handle, SIGNAL_PEER_CLOSED, 0, &mut observed);
+    observed as usize & SIGNAL_PEER_CLOSED == 0
+}
+
+fn register(
+    names: &mut BTreeMap<String, Entry>,
+    name: String,
+    handle: Option<usize>,
+    sender: usize,
+) -> isize {
+    let handle = match handle {
+        Some(handle) => handle,
+        None => return EINVAL,
+    };
+    if let Some(entry) = names.get(&name) {
+        if entry.owner != sender && alive(entry) {
+            handle_close(handle);
+            return EEXIST;
+        }
+        handle_close(entry.handle);
+    }
+    names.insert(name, Entry { handle, owner: sender });
+    0
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    println!("[nameserver] serving on handle {}", BOOTSTRAP_HANDLE);
+    let mut names: BTreeMap<String, Entry> = BTreeMap::new();
+    loop {
+        let mut request = [0u8; NS_REQUEST_SIZE];
+        let mut handles = [0usize; 1];
+        let mut info = MessageInfo::default();
+        let len = channel_read_handles(BOOTSTRAP_HANDLE, &mut request, &mut handles, 0, &mut info);
+        if len == EMSGSIZE {
+            // longer than NS_MAX_NAME or with more than one handle, the request
+            // is still queued, take it off to reject it
+            let mut long = vec![0u8; info.len];
+            let mut extra = vec![0usize; info.handles];
+            channel_read_handles(BOOTSTRAP_HANDLE, &mut long, &mut extra, 0, &mut info);
+            for handle in extra[..info.handles].iter() {
+                handle_close(*handle);
+            }
+            if info.txid != 0 {
+                channel_reply(BOOTSTRAP_HANDLE, info.txid, &EINVAL.to_le_bytes());
+            }
+            continue;
+        }
+        let handle = if info.handles > 0 { Some(handles[0]) } else { None };
+        let name = if len > 1 {
+            core::str::from_utf8(&request[1..len as usize]).ok().map(String::from)
+        } else {
+            None
+        };
+        let name = match name {
+            Some(name) => name,
+            None => {
+                if let Some(handle) = handle {
+                    handle_close(handle);
+                }
+                if info.txid != 0 {
+                    channel_reply(BOOTSTRAP_HANDLE, info.txid, &EINVAL.to_le_bytes());
+                }
+                continue;
+            }
+        };
+        let mut reply_handle = None;
+        let status = match request[0] {
+            NS_REGISTER => register(&mut names, name, handle, info.sender),
+            // answered with the pid serving the name, which the kernel
+            // returns from service_lookup
+            NS_LOOKUP => match names.get(&name).filter(|entry| alive(entry)) {
+                Some(entry) => {
+                    let rights = RIGHT_WRITE | RIGHT_DUPLICATE | RIGHT_TRANSFER;
+                    let copy = handle_duplicate(entry.handle, rights);
+                    if copy < 0 {
+                        copy
+                    } else {
+                        reply_handle = Some(copy as usize);
+                        entry.owner as isize
+                    }
+                }
+                None => ENOENT,
+            },
+            NS_UNREGISTER => match names.get(&name) {
+                Some(entry) if entry.owner == info.sender => {
+                    handle_close(entry.handle);
+                    names.remove(&name);
+                    0
+                }
+                Some(_) => EACCES,
+                None => ENOENT,
+            },
+            _ => EINVAL,
+        };
+        if request[0] != NS_REGISTER {
+            if let Some(handle) = handle {
+                handle_close(handle);
+            }
+        }
+        // the kernel forwards the registrations of the manifest and of
+        // restarts without waiting for an answer
+        if info.txid != 0 {
+            let reply = status.to_le_bytes();
+            match reply_handle {
+                Some(copy) => channel_reply_handles(BOOTSTRAP_HANDLE, info.txid, &reply, &[copy]),
+                None => channel_reply(BOOTSTRAP_HANDLE, info.txid, &reply),
+            };
+        }
+    }
+}
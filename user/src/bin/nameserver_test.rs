@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    channel_create, channel_read, channel_write, errno::{EACCES, EBADF, EEXIST, ENOENT}, exit,
+    fork, getpid, nameserver, service_connect, service_lookup, service_wait, waitpid,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let probe = nameserver::lookup("com.test.none");
+    if probe == EBADF {
+        println!("kernel built without user-nameserver, skipped");
+        return 0;
+    }
+    assert_eq!(probe, ENOENT);
+
+    let mut handles = [0usize; 2];
+    assert_eq!(channel_create(&mut handles), 0);
+    assert_eq!(nameserver::register("com.test.ns", handles[1]), 0);
+    let server = nameserver::lookup("com.test.ns");
+    assert!(server >= 0);
+    channel_write(server as usize, b"ns");
+    let mut buf = [0u8; 2];
+    assert_eq!(channel_read(handles[0], &mut buf), 2);
+    assert_eq!(&buf, b"ns");
+    println!("register and lookup ok");
+
+    // the name syscalls are served by the name server too
+    assert_eq!(service_lookup("com.test.ns\0"), getpid());
+    assert_eq!(service_wait("com.test.ns\0", 0), getpid());
+    let connected = service_connect("com.test.ns\0");
+    assert!(connected >= 0);
+    channel_write(connected as usize, b"sc");
+    assert_eq!(channel_read(handles[0], &mut buf), 2);
+    assert_eq!(&buf, b"sc");
+    println!("service syscalls through the name server ok");
+
+    let pid = fork();
+    if pid == 0 {
+        let mut other = [0usize; 2];
+        channel_create(&mut other);
+        assert_eq!(nameserver::register("com.test.ns", other[1]), EEXIST);
+        assert_eq!(nameserver::unregister("com.test.ns"), EACCES);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    assert_eq!(exit_code, 0);
+
+    assert_eq!(nameserver::unregister("com.test.ns"), 0);
+    assert_eq!(nameserver::lookup("com.test.ns"), ENOENT);
+    println!("nameserver test passed!");
+    0
+}
@@ -0,0 +1,14 @@
+#![no_std]
+#![no_main]
+
+use user_lib::get_time;
+
+extern crate user_lib;
+
+// Stay ready for 300 ms, in no partition
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time();
+    while get_time() < start + 300 {}
+    0
+}
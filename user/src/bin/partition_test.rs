@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{create_task, get_time, task_partition, waitpid};
+
+#[macro_use]
+extern crate user_lib;
+
+// Spin for duration_ms and return the longest stretch the task did not run
+fn longest_gap(duration_ms: isize) -> isize {
+    let start = get_time();
+    let mut last = start;
+    let mut gap = 0;
+    while last < start + duration_ms {
+        let now = get_time();
+        gap = gap.max(now - last);
+        last = now;
+    }
+    gap
+}
+
+// Runs in the partition of the example in user/partitions.schedule, 20 ms out
+// of every 50 ms
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut name = [0u8; 16];
+    if task_partition(0, &mut name) < 0 {
+        println!("no partition schedule active, partition test skipped");
+        return 0;
+    }
+    // a ready task in no partition owns the 30 ms window of the rest
+    let spin = create_task("partition_spin\0");
+    assert!(spin > 0);
+    let gap = longest_gap(200);
+    assert!(gap >= 20, "never off the cpu for more than {} ms", gap);
+    println!("window enforced ok");
+
+    assert_eq!(waitpid(spin as usize, &mut 0), spin);
+    // nothing else is ready, the window of the rest is donated
+    let gap = longest_gap(200);
+    assert!(gap < 20, "idle for {} ms with donated time", gap);
+    println!("unused time donated ok");
+    println!("partition test passed!");
+    0
+}
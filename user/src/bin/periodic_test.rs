@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{errno::EINVAL, get_time, periodic_stats, set_periodic, wait_period, PeriodicStats};
+
+#[macro_use]
+extern crate user_lib;
+
+const PERIOD_MS: isize = 50;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(wait_period(), EINVAL);
+    // the budget must fit in the deadline, and the deadline in the period
+    assert_eq!(set_periodic(50, 60, 0), EINVAL);
+    assert_eq!(set_periodic(50, 20, 80), EINVAL);
+
+    let start = get_time();
+    assert_eq!(set_periodic(PERIOD_MS as usize, 20, 0), 0);
+    for job in 1..=5 {
+        assert_eq!(wait_period(), 0);
+        assert!(get_time() >= start + job * PERIOD_MS);
+    }
+    let mut stats = PeriodicStats::default();
+    assert_eq!(periodic_stats(0, &mut stats), 0);
+    assert_eq!(stats.jobs, 5);
+    assert_eq!(stats.deadline_misses, 0);
+    assert_eq!(stats.budget_overruns, 0);
+    println!("released every {} ms ok", PERIOD_MS);
+
+    // a job running past both its budget and its deadline
+    let release = get_time();
+    while get_time() < release + 70 {}
+    assert_eq!(wait_period(), 0);
+    assert_eq!(periodic_stats(0, &mut stats), 0);
+    assert_eq!(stats.jobs, 6);
+    assert_eq!(stats.deadline_misses, 1);
+    assert_eq!(stats.budget_overruns, 1);
+    println!("deadline miss and budget overrun counted ok");
+
+    assert_eq!(set_periodic(0, 0, 0), 0);
+    assert_eq!(periodic_stats(0, &mut stats), EINVAL);
+    println!("periodic test passed!");
+    0
+}
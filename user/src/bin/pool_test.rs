@@ -0,0 +1,124 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    channel_call, channel_create, channel_read, channel_read_info, channel_reply, channel_write,
+    errno::{EEXIST, ENOENT},
+    exit, fork, register, service_connect, service_join, service_leave, service_lookup,
+    service_wait, sleep, waitpid, MessageInfo, DISPATCH_LEAST_QUEUED, DISPATCH_ROUND_ROBIN,
+    MAILBOX_HANDLE, TIMEOUT_INFINITE,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+const POOL: &str = "com.test.pool\0";
+const QUEUED_POOL: &str = "com.test.queued\0";
+
+// Answer calls until told to leave the pool
+fn instance(pool: &str, dispatch: usize) -> ! {
+    assert_eq!(service_join(pool, dispatch), 0);
+    loop {
+        let mut buf = [0u8; 8];
+        let mut info = MessageInfo::default();
+        let len = channel_read_info(MAILBOX_HANDLE, &mut buf, 0, &mut info);
+        if len < 0 {
+            continue;
+        }
+        let leave = &buf[..len as usize] == b"leave";
+        if leave {
+            assert_eq!(service_leave(pool), 0);
+        }
+        channel_reply(MAILBOX_HANDLE, info.txid, b"ok");
+        if leave {
+            exit(0);
+        }
+    }
+}
+
+// Join without reading the mailbox until hold is written to
+fn idle_instance(hold: usize) -> ! {
+    assert_eq!(service_join(QUEUED_POOL, DISPATCH_LEAST_QUEUED), 0);
+    let mut buf = [0u8; 4];
+    channel_read(hold, &mut buf);
+    assert_eq!(service_leave(QUEUED_POOL), 0);
+    exit(0);
+}
+
+// Call the pool and return the pid of the instance which answered
+fn call(pool: usize, request: &[u8]) -> usize {
+    let mut reply = [0u8; 2];
+    let mut info = MessageInfo::default();
+    assert_eq!(channel_call(pool, request, &mut reply, TIMEOUT_INFINITE, &mut info), 2);
+    info.sender
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pids = [0usize; 2];
+    for pid in pids.iter_mut() {
+        let child = fork();
+        if child == 0 {
+            instance(POOL, DISPATCH_ROUND_ROBIN);
+        }
+        *pid = child as usize;
+    }
+    // let both instances join
+    sleep(50);
+    let pool = service_connect(POOL);
+    assert!(pool >= 0);
+    let pool = pool as usize;
+
+    let mut senders = [0usize; 4];
+    for sender in senders.iter_mut() {
+        *sender = call(pool, b"ping");
+    }
+    assert!(pids.contains(&senders[0]) && pids.contains(&senders[1]));
+    assert_ne!(senders[0], senders[1]);
+    assert_eq!(senders[0], senders[2]);
+    assert_eq!(senders[1], senders[3]);
+    println!("round-robin ok");
+    // the name belongs to the pool
+    assert_eq!(register("hello_world\0", POOL), EEXIST);
+
+    // the instance which got it leaves, the other one takes every message
+    let left = call(pool, b"leave");
+    let remaining = if left == pids[0] { pids[1] } else { pids[0] };
+    assert_eq!(call(pool, b"ping"), remaining);
+    assert_eq!(call(pool, b"ping"), remaining);
+    assert_eq!(call(pool, b"leave"), remaining);
+    for pid in pids.iter() {
+        waitpid(*pid, &mut 0);
+    }
+    assert_eq!(service_lookup(POOL), ENOENT);
+
+    // a message waiting for the idle instance sends the calls to the other one
+    let mut hold = [0usize; 2];
+    assert_eq!(channel_create(&mut hold), 0);
+    let idle = fork();
+    if idle == 0 {
+        idle_instance(hold[1]);
+    }
+    assert_eq!(service_wait(QUEUED_POOL, TIMEOUT_INFINITE), idle);
+    let busy = fork();
+    if busy == 0 {
+        instance(QUEUED_POOL, DISPATCH_LEAST_QUEUED);
+    }
+    sleep(50);
+    let pool = service_connect(QUEUED_POOL);
+    assert!(pool >= 0);
+    let pool = pool as usize;
+    // both mailboxes are empty, the first instance gets it
+    assert_eq!(channel_write(pool, b"hold"), 0);
+    for _ in 0..3 {
+        assert_eq!(call(pool, b"ping"), busy as usize);
+    }
+    assert_eq!(call(pool, b"leave"), busy as usize);
+    channel_write(hold[0], b"done");
+    waitpid(idle as usize, &mut 0);
+    waitpid(busy as usize, &mut 0);
+    assert_eq!(service_lookup(QUEUED_POOL), ENOENT);
+    println!("least-queued ok");
+    println!("pool test passed!");
+    0
+}
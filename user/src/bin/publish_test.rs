@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    channel_create, channel_read, channel_write,
+    errno::{EACCES, EEXIST, EINVAL, ENOENT},
+    exit, fork, getpid, service_connect, service_lookup, service_publish, service_publish_handle,
+    service_remove, service_unpublish, service_wait, topic_open, waitpid,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a running task claims names for itself
+    assert_eq!(service_publish("com.test.self\0"), 0);
+    assert_eq!(service_lookup("com.test.self\0"), getpid());
+    let mut handles = [0usize; 2];
+    assert_eq!(channel_create(&mut handles), 0);
+    assert_eq!(service_publish_handle("com.test.alt\0", handles[1]), 0);
+    let alt = service_connect("com.test.alt\0");
+    assert!(alt >= 0);
+    channel_write(alt as usize, b"hi");
+    let mut buf = [0u8; 2];
+    assert_eq!(channel_read(handles[0], &mut buf), 2);
+    assert_eq!(&buf, b"hi");
+    println!("publish ok");
+
+    // the names belong to this task as long as it lives
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(service_publish("com.test.self\0"), EEXIST);
+        assert_eq!(service_unpublish("com.test.alt\0"), EACCES);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    assert_eq!(exit_code, 0);
+
+    assert!(topic_open("com.test.news\0") >= 0);
+    assert_eq!(service_publish("com.test.news\0"), EEXIST);
+
+    assert_eq!(service_unpublish("com.test.self\0"), 0);
+    assert_eq!(service_lookup("com.test.self\0"), ENOENT);
+    assert_eq!(service_unpublish("com.test.self\0"), ENOENT);
+
+    // a subtree goes away at once, but only if nobody else serves in it
+    let mut done = [0usize; 2];
+    assert_eq!(channel_create(&mut done), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(service_publish("com.test.other.x\0"), 0);
+        let mut buf = [0u8; 4];
+        channel_read(done[1], &mut buf);
+        exit(0);
+    }
+    assert_eq!(service_wait("com.test.other.x\0", 1000), pid);
+    assert_eq!(service_remove("com.test.other\0"), EACCES);
+    assert_eq!(service_lookup("com.test.other.x\0"), pid);
+    channel_write(done[0], b"done");
+    waitpid(pid as usize, &mut exit_code);
+    assert_eq!(service_publish("com.test.tree.a\0"), 0);
+    assert_eq!(service_publish("com.test.tree.b.c\0"), 0);
+    assert_eq!(service_remove("com.test.tree\0"), 0);
+    assert_eq!(service_lookup("com.test.tree.a\0"), ENOENT);
+    assert_eq!(service_lookup("com.test.tree.b.c\0"), ENOENT);
+    assert_eq!(service_remove("com.test.tree\0"), ENOENT);
+    assert_eq!(service_remove("\0"), EINVAL);
+    println!("remove ok");
+    println!("publish test passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    errno::{EEXIST, ENOENT},
+    getpid, register, register_with_restart, service_join, service_leave, service_lookup,
+    service_publish, service_unpublish, sleep, RestartPolicy, DISPATCH_ROUND_ROBIN,
+    RESTART_ALWAYS, RESTART_ON_FAILURE,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // without a policy the name goes away with the task
+    assert!(register("hello_world\0", "com.test.hello\0") > 0);
+    sleep(100);
+    assert_eq!(service_lookup("com.test.hello\0"), ENOENT);
+    println!("deregistration on exit ok");
+
+    // a clean exit is not restarted on failure only
+    let on_failure = RestartPolicy { mode: RESTART_ON_FAILURE, max_retries: 3, backoff_ms: 10 };
+    assert!(register_with_restart("hello_world\0", "com.test.clean\0", &on_failure) > 0);
+    sleep(100);
+    assert_eq!(service_lookup("com.test.clean\0"), ENOENT);
+
+    // exit0 fails every time, it is restarted until the retries run out
+    let always = RestartPolicy { mode: RESTART_ALWAYS, max_retries: 2, backoff_ms: 10 };
+    let first = register_with_restart("exit0\0", "com.test.crashy\0", &always);
+    assert!(first > 0);
+    // backoff of 10 + 20 ms before giving up
+    sleep(300);
+    assert_eq!(service_lookup("com.test.crashy\0"), ENOENT);
+    println!("restart ok");
+
+    // a name held by a live task is not taken over
+    assert_eq!(service_publish("com.test.held\0"), 0);
+    assert_eq!(register("hello_world\0", "com.test.held\0"), EEXIST);
+    assert_eq!(service_lookup("com.test.held\0"), getpid());
+    assert_eq!(service_unpublish("com.test.held\0"), 0);
+
+    // a pool formed during the backoff keeps the name
+    let retry = RestartPolicy { mode: RESTART_ALWAYS, max_retries: 3, backoff_ms: 100 };
+    assert!(register_with_restart("exit0\0", "com.test.pooled\0", &retry) > 0);
+    sleep(20);
+    assert_eq!(service_join("com.test.pooled\0", DISPATCH_ROUND_ROBIN), 0);
+    sleep(200);
+    assert_eq!(service_lookup("com.test.pooled\0"), getpid());
+    assert_eq!(service_leave("com.test.pooled\0"), 0);
+    assert_eq!(service_lookup("com.test.pooled\0"), ENOENT);
+    println!("restart test passed!");
+    0
+}
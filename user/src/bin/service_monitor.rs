@@ -1,7 +1,12 @@
 #![no_std]
 #![no_main]
 
-use user_lib::channel_read;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{
+    channel_read_handles, channel_reply, channel_write, errno::EMSGSIZE, handle_close, MessageInfo, MAILBOX_HANDLE,
+};
 
 #[macro_use]
 extern crate user_lib;
@@ -9,12 +14,33 @@ extern crate user_lib;
 #[no_mangle]
 pub fn main() -> i32 {
     loop {
-        let mut buf = [0u8; 27];
-        let result_code = channel_read(&mut buf);
-        if result_code == -1 {
+        let mut buf = vec![0u8; 16];
+        let mut handles = [0usize; 4];
+        let mut info = MessageInfo::default();
+        let mut result_code = channel_read_handles(MAILBOX_HANDLE, &mut buf, &mut handles, 0, &mut info);
+        if result_code == EMSGSIZE {
+            // the message is still queued, retry with a buffer that fits
+            buf.resize(info.len, 0);
+            result_code = channel_read_handles(MAILBOX_HANDLE, &mut buf, &mut handles, 0, &mut info);
+        }
+        if result_code < 0 {
             continue;
         }
-        println!("service_monitor receive = {:?}", buf);
+        println!(
+            "service_monitor receive {} bytes from pid {} = {:?}",
+            result_code,
+            info.sender,
+            &buf[..result_code as usize]
+        );
+        if info.txid != 0 {
+            // the caller blocks in channel_call until this reply
+            channel_reply(MAILBOX_HANDLE, info.txid, b"pong");
+        }
+        // acknowledge through every reply channel handed over
+        for reply in handles[..info.handles].iter() {
+            channel_write(*reply, b"ack");
+            handle_close(*reply);
+        }
     }
     0
-}
\ No newline at end of file
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{exit, fork, get_time, sleep, sleep_until, try_waitpid, waitpid};
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time();
+    sleep(30);
+    assert!(get_time() >= start + 30);
+    let deadline = get_time() + 25;
+    assert_eq!(sleep_until(deadline as usize), 0);
+    assert!(get_time() >= deadline);
+    // a deadline in the past returns right away
+    assert_eq!(sleep_until(0), 0);
+    println!("sleep ok");
+
+    let start = get_time();
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        exit(7);
+    }
+    let pid = pid as usize;
+    let mut exit_code = 0;
+    assert_eq!(try_waitpid(pid, &mut exit_code), -2);
+    // blocks until the child is a zombie
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, 7);
+    assert!(get_time() >= start + 50);
+    assert_eq!(waitpid(pid, &mut exit_code), -1);
+    println!("sleep test passed!");
+    0
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    channel_read, channel_stats, channel_try_read, errno::EMSGSIZE, exit, fork, handle_close,
+    topic_open, topic_publish, topic_subscribe, waitpid, ChannelLimits, ChannelStats,
+    TOPIC_DROP_NEWEST, TOPIC_DROP_OLDEST,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let limits = ChannelLimits { max_msgs: 2, max_bytes: 64 };
+    let oldest = topic_subscribe("com.test.sensor.imu\0", TOPIC_DROP_OLDEST, Some(&limits));
+    let newest = topic_subscribe("com.test.sensor.imu\0", TOPIC_DROP_NEWEST, Some(&limits));
+    assert!(oldest >= 0 && newest >= 0);
+    let imu = topic_open("com.test.sensor.imu\0");
+    assert!(imu >= 0);
+
+    for sample in [b"s1", b"s2", b"s3"].iter() {
+        topic_publish(imu as usize, *sample);
+    }
+    let mut buf = [0u8; 2];
+    // drop-oldest keeps the latest samples, drop-newest the first ones
+    channel_read(oldest as usize, &mut buf);
+    assert_eq!(&buf, b"s2");
+    channel_read(oldest as usize, &mut buf);
+    assert_eq!(&buf, b"s3");
+    channel_read(newest as usize, &mut buf);
+    assert_eq!(&buf, b"s1");
+    channel_read(newest as usize, &mut buf);
+    assert_eq!(&buf, b"s2");
+    let mut stats = ChannelStats::default();
+    channel_stats(newest as usize, &mut stats);
+    assert_eq!(stats.dropped, 1);
+    // larger than the queue of every subscriber
+    assert_eq!(topic_publish(imu as usize, &[0u8; 65]), EMSGSIZE);
+    println!("overflow policies ok");
+
+    // a subscriber which exits is unsubscribed
+    let pid = fork();
+    if pid == 0 {
+        let sub = topic_subscribe("com.test.sensor.imu\0", TOPIC_DROP_OLDEST, None);
+        assert!(sub >= 0);
+        exit(0);
+    }
+    waitpid(pid as usize, &mut 0);
+    assert_eq!(topic_publish(imu as usize, b"s4"), 2);
+    handle_close(newest as usize);
+    assert_eq!(topic_publish(imu as usize, b"s5"), 1);
+    assert_eq!(channel_try_read(oldest as usize, &mut buf), 2);
+    println!("topic test passed!");
+    0
+}
@@ -0,0 +1,20 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{channel_read, heartbeat, service_watchdog, sleep, MAILBOX_HANDLE, WATCHDOG_RESTART};
+
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // registered as com.test.hang by watchdog_test
+    assert_eq!(service_watchdog("com.test.hang\0", 50, WATCHDOG_RESTART, None), 0);
+    for _ in 0..3 {
+        assert_eq!(heartbeat(), 0);
+        sleep(20);
+    }
+    // stuck on a message nobody sends, the watchdog kills the blocked task
+    let mut buf = [0u8; 1];
+    channel_read(MAILBOX_HANDLE, &mut buf);
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+use user_lib::{
+    channel_read_info, errno::{EAGAIN, ENOENT}, get_time, getpid, heartbeat, register_with_restart,
+    service_lookup, service_publish, service_watchdog, sleep, MessageInfo, RestartPolicy,
+    CHANNEL_NONBLOCK, MAILBOX_HANDLE, RESTART_ON_FAILURE, WATCHDOG_NOTIFY,
+};
+
+#[macro_use]
+extern crate user_lib;
+
+// Poll until service_lookup(path) satisfies done, or a second passes
+fn wait_lookup(path: &str, done: impl Fn(isize) -> bool) -> isize {
+    let start = get_time();
+    loop {
+        let pid = service_lookup(path);
+        if done(pid) || get_time() - start > 1000 {
+            return pid;
+        }
+        sleep(5);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // this task supervises itself
+    assert_eq!(service_publish("com.test.watched\0"), 0);
+    assert_eq!(service_publish("com.test.supervisor\0"), 0);
+    assert_eq!(
+        service_watchdog("com.test.watched\0", 50, WATCHDOG_NOTIFY, Some("com.test.supervisor\0")),
+        0
+    );
+    let mut buf = [0u8; 32];
+    let mut info = MessageInfo::default();
+    for _ in 0..10 {
+        assert_eq!(heartbeat(), 0);
+        sleep(20);
+    }
+    assert_eq!(channel_read_info(MAILBOX_HANDLE, &mut buf, CHANNEL_NONBLOCK, &mut info), EAGAIN);
+    sleep(100);
+    let len = channel_read_info(MAILBOX_HANDLE, &mut buf, CHANNEL_NONBLOCK, &mut info);
+    assert_eq!(&buf[..len as usize], b"com.test.watched");
+    assert_eq!(info.sender as isize, getpid());
+    assert_eq!(service_watchdog("com.test.watched\0", 0, WATCHDOG_NOTIFY, None), 0);
+    println!("watchdog notify ok");
+
+    // a service hung in a blocking read is killed and restarted once, then given up
+    let policy = RestartPolicy { mode: RESTART_ON_FAILURE, max_retries: 1, backoff_ms: 10 };
+    let first = register_with_restart("watchdog_hang\0", "com.test.hang\0", &policy);
+    assert!(first > 0);
+    let second = wait_lookup("com.test.hang\0", |pid| pid > 0 && pid != first);
+    assert!(second > 0 && second != first);
+    assert_eq!(wait_lookup("com.test.hang\0", |pid| pid == ENOENT), ENOENT);
+    println!("watchdog test passed!");
+    0
+}
@@ -0,0 +1,12 @@
+// Error codes returned by the kernel, see kernel/src/errno.rs.
+
+pub const ENOENT: isize = -2;
+pub const EBADF: isize = -9;
+pub const EAGAIN: isize = -11;
+pub const ENOMEM: isize = -12;
+pub const EACCES: isize = -13;
+pub const EEXIST: isize = -17;
+pub const EINVAL: isize = -22;
+pub const EPIPE: isize = -32;
+pub const EMSGSIZE: isize = -90;
+pub const ETIMEDOUT: isize = -110;
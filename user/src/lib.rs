@@ -6,6 +6,8 @@
 
 #[macro_use]
 pub mod console;
+pub mod errno;
+pub mod nameserver;
 mod syscall;
 mod lang_items;
 
@@ -49,33 +51,45 @@ pub fn write(fd: usize, buf: &[u8]) -> isize { sys_write(fd, buf) }
 pub fn exit(exit_code: i32) -> ! { sys_exit(exit_code); }
 pub fn yield_() -> isize { sys_yield() }
 pub fn get_time() -> isize { sys_get_time() }
+/// Priority of a new task, a larger value runs first.
+pub const DEFAULT_PRIORITY: usize = 16;
+pub const MAX_PRIORITY: usize = 255;
+/// Set the priority of pid, 0 for the caller, which must be the task or its parent.
+/// EINVAL outside 1..=MAX_PRIORITY, EACCES above the priority of the caller
+/// unless it is initproc or was started by the service manifest.
+pub fn set_priority(pid: usize, priority: usize) -> isize { sys_set_priority(pid, priority) }
+pub fn get_priority(pid: usize) -> isize { sys_get_priority(pid) }
 pub fn getpid() -> isize { sys_getpid() }
 pub fn fork() -> isize { sys_fork() }
 pub fn exec(path: &str) -> isize { sys_exec(path) }
+/// Block until a child exits and return its pid, or -1 without children.
 pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => { yield_(); }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    sys_waitpid(-1, exit_code as *mut _, 0)
 }
 
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => { yield_(); }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+
+pub const WNOHANG: usize = 1;
+
+/// Like waitpid, but return -2 instead of blocking while the child is running.
+pub fn try_waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    sys_waitpid(pid as isize, exit_code as *mut _, WNOHANG)
 }
+
+/// Block for at least period_ms, other tasks run meanwhile.
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
-    }
+    sys_nanosleep(period_ms.saturating_mul(1_000_000));
+}
+
+pub fn nanosleep(duration_ns: usize) -> isize {
+    sys_nanosleep(duration_ns)
+}
+
+/// Block until get_time reaches deadline_ms.
+pub fn sleep_until(deadline_ms: usize) -> isize {
+    sys_sleep_until(deadline_ms)
 }
 
 pub fn create_task(path: &str) -> isize {
@@ -94,14 +108,385 @@ pub fn munmap(start: usize, len: usize) -> isize {
     sys_munmap(start, len)
 }
 
-pub fn channel_read(buf: &mut [u8]) -> isize {
-    sys_channel_read(buf, buf.len())
+pub const CHANNEL_NONBLOCK: usize = 1 << 0;
+
+/// Handle 0 of every task is the read end of its mailbox.
+pub const MAILBOX_HANDLE: usize = 0;
+
+pub const RIGHT_READ: usize = 1 << 0;
+pub const RIGHT_WRITE: usize = 1 << 1;
+pub const RIGHT_DUPLICATE: usize = 1 << 2;
+pub const RIGHT_TRANSFER: usize = 1 << 3;
+
+pub const SIGNAL_READABLE: usize = 1 << 0;
+pub const SIGNAL_WRITABLE: usize = 1 << 1;
+/// The other endpoint of the channel is closed, e.g. because the service exited.
+pub const SIGNAL_PEER_CLOSED: usize = 1 << 2;
+pub const SIGNAL_TIMER_FIRED: usize = 1 << 3;
+
+pub const WAIT_HANDLE: u32 = 0;
+pub const WAIT_FD: u32 = 1;
+
+/// One entry of object_wait_many: a handle or file descriptor, depending on kind,
+/// and the signals to wait for. The kernel stores the asserted signals in observed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct WaitItem {
+    pub kind: u32,
+    pub signals: u32,
+    pub id: usize,
+    pub observed: u32,
+}
+
+impl WaitItem {
+    pub fn handle(handle: usize, signals: usize) -> Self {
+        Self { kind: WAIT_HANDLE, signals: signals as u32, id: handle, observed: 0 }
+    }
+
+    pub fn fd(fd: usize, signals: usize) -> Self {
+        Self { kind: WAIT_FD, signals: signals as u32, id: fd, observed: 0 }
+    }
+}
+
+/// Wait for the reply of a channel_call, or for a signal, without a deadline.
+pub const TIMEOUT_INFINITE: usize = usize::MAX;
+
+/// Length, sender pid, handle count and transaction id of a received message.
+/// A nonzero txid marks a request sent by channel_call, answer it with channel_reply.
+/// Pages granted with the message are mapped at grant_addr, grant_len bytes long.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MessageInfo {
+    pub len: usize,
+    pub sender: usize,
+    pub handles: usize,
+    pub txid: usize,
+    pub grant_len: usize,
+    pub grant_addr: usize,
+}
+
+/// Bounds on the messages queued at each endpoint of a channel.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelLimits {
+    pub max_msgs: usize,
+    pub max_bytes: usize,
+}
+
+/// Occupancy of the receive queue of an endpoint, hwm_* are the high-water marks.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ChannelStats {
+    pub msgs: usize,
+    pub bytes: usize,
+    pub max_msgs: usize,
+    pub max_bytes: usize,
+    pub hwm_msgs: usize,
+    pub hwm_bytes: usize,
+    // messages a topic discarded because the queue was full
+    pub dropped: usize,
+}
+
+pub fn channel_create(handles: &mut [usize; 2]) -> isize {
+    sys_channel_create(handles, core::ptr::null())
+}
+
+pub fn channel_create_bounded(handles: &mut [usize; 2], limits: &ChannelLimits) -> isize {
+    sys_channel_create(handles, limits as *const _)
+}
+
+pub fn channel_stats(handle: usize, stats: &mut ChannelStats) -> isize {
+    sys_channel_stats(handle, stats as *mut _)
+}
+
+pub fn channel_read(handle: usize, buf: &mut [u8]) -> isize {
+    sys_channel_read(handle, buf, &mut [], 0, core::ptr::null_mut())
+}
+
+pub fn channel_try_read(handle: usize, buf: &mut [u8]) -> isize {
+    sys_channel_read(handle, buf, &mut [], CHANNEL_NONBLOCK, core::ptr::null_mut())
+}
+
+/// Return the message length, or EMSGSIZE with the message left queued
+/// when buf is too short. info is filled in both cases. ENOMEM, also with the
+/// message left queued, if its granted pages do not fit in the address space.
+pub fn channel_read_info(handle: usize, buf: &mut [u8], flags: usize, info: &mut MessageInfo) -> isize {
+    sys_channel_read(handle, buf, &mut [], flags, info as *mut _)
+}
+
+/// Like channel_read_info, the handles carried by the message are installed
+/// in this task and stored in handles[..info.handles].
+pub fn channel_read_handles(
+    handle: usize,
+    buf: &mut [u8],
+    handles: &mut [usize],
+    flags: usize,
+    info: &mut MessageInfo,
+) -> isize {
+    sys_channel_read(handle, buf, handles, flags, info as *mut _)
+}
+
+/// Blocks while the queue of the other endpoint is full.
+pub fn channel_write(handle: usize, buf: &[u8]) -> isize {
+    sys_channel_write(handle, buf, &[], 0)
+}
+
+/// Return EAGAIN instead of blocking when the queue of the other endpoint is full.
+pub fn channel_try_write(handle: usize, buf: &[u8]) -> isize {
+    sys_channel_write(handle, buf, &[], CHANNEL_NONBLOCK)
+}
+
+/// Send buf along with the pages of a whole mmap region, which is unmapped from
+/// this task and mapped into the receiver without copying.
+pub fn channel_write_grant(handle: usize, buf: &[u8], grant_addr: usize, grant_len: usize) -> isize {
+    sys_channel_grant(handle, buf, grant_addr, grant_len, 0)
+}
+
+/// Handles a single message may carry.
+pub const MAX_HANDLES_PER_MSG: usize = 64;
+
+/// Send buf along with handles, which are moved out of this task.
+pub fn channel_write_handles(handle: usize, buf: &[u8], handles: &[usize]) -> isize {
+    sys_channel_write(handle, buf, handles, 0)
+}
+
+/// Send request and block until the server replies or timeout_ms passes, then
+/// return the reply length. Fails with ETIMEDOUT, or EMSGSIZE if the reply does not fit;
+/// it is then kept for channel_take_reply with info.txid and a buffer of info.len bytes.
+pub fn channel_call(
+    handle: usize,
+    request: &[u8],
+    reply: &mut [u8],
+    timeout_ms: usize,
+    info: &mut MessageInfo,
+) -> isize {
+    sys_channel_call(handle, request, &[], reply, &mut [], timeout_ms, info as *mut _)
+}
+
+/// Like channel_call, moving request_handles to the server and receiving
+/// the handles of the reply in reply_handles[..info.handles].
+pub fn channel_call_handles(
+    handle: usize,
+    request: &[u8],
+    request_handles: &[usize],
+    reply: &mut [u8],
+    reply_handles: &mut [usize],
+    timeout_ms: usize,
+    info: &mut MessageInfo,
+) -> isize {
+    sys_channel_call(handle, request, request_handles, reply, reply_handles, timeout_ms, info as *mut _)
+}
+
+/// Read the reply of call txid kept after channel_call returned EMSGSIZE.
+pub fn channel_take_reply(
+    handle: usize,
+    txid: usize,
+    reply: &mut [u8],
+    reply_handles: &mut [usize],
+    info: &mut MessageInfo,
+) -> isize {
+    sys_channel_take_reply(handle, txid, reply, reply_handles, info as *mut _)
+}
+
+/// Answer the request with transaction id txid read from handle.
+pub fn channel_reply(handle: usize, txid: usize, buf: &[u8]) -> isize {
+    sys_channel_reply(handle, txid, buf, &[])
+}
+
+pub fn channel_reply_handles(handle: usize, txid: usize, buf: &[u8], handles: &[usize]) -> isize {
+    sys_channel_reply(handle, txid, buf, handles)
+}
+
+/// Block until the object behind handle asserts any of signals. Return 0, or
+/// ETIMEDOUT once timeout_ms passes; the asserted signals are stored in observed.
+pub fn object_wait_one(handle: usize, signals: usize, timeout_ms: usize, observed: &mut u32) -> isize {
+    sys_object_wait_one(handle, signals, timeout_ms, observed as *mut _)
 }
 
-pub fn channel_write(path: &str, buf: &[u8]) -> isize {
-    sys_channel_write(path, buf, buf.len())
+/// Block until any entry of items asserts one of its signals. Return the number
+/// of entries which fired, or ETIMEDOUT once timeout_ms passes.
+pub fn object_wait_many(items: &mut [WaitItem], timeout_ms: usize) -> isize {
+    sys_object_wait_many(items, timeout_ms)
+}
+
+/// Create a one-shot timer, it asserts SIGNAL_TIMER_FIRED once expired.
+pub fn timer_create() -> isize {
+    sys_timer_create()
+}
+
+/// Arm the timer to fire timeout_ms from now.
+pub fn timer_set(handle: usize, timeout_ms: usize) -> isize {
+    sys_timer_set(handle, timeout_ms)
+}
+
+pub fn timer_cancel(handle: usize) -> isize {
+    sys_timer_cancel(handle)
+}
+
+pub const TOPIC_DROP_OLDEST: usize = 0;
+pub const TOPIC_DROP_NEWEST: usize = 1;
+
+/// Get a handle to publish on a topic, EEXIST if the name belongs to a service.
+pub fn topic_open(path: &str) -> isize {
+    sys_topic_open(path)
+}
+
+/// Subscribe to a topic, read the published messages from the returned handle.
+/// policy says what to drop when more than limits allows are queued.
+pub fn topic_subscribe(path: &str, policy: usize, limits: Option<&ChannelLimits>) -> isize {
+    sys_topic_subscribe(path, policy, limits.map_or(core::ptr::null(), |limits| limits as *const _))
+}
+
+/// Return the number of subscribers the message was queued for.
+pub fn topic_publish(handle: usize, buf: &[u8]) -> isize {
+    sys_topic_publish(handle, buf)
+}
+
+/// Get a write handle to the mailbox of a registered service.
+pub fn service_connect(path: &str) -> isize {
+    sys_service_connect(path)
+}
+
+pub fn handle_duplicate(handle: usize, rights: usize) -> isize {
+    sys_handle_duplicate(handle, rights)
+}
+
+pub fn handle_close(handle: usize) -> isize {
+    sys_handle_close(handle)
+}
+
+pub const SERVICE_LIST_RECURSIVE: usize = 1 << 0;
+
+/// Fill buf with the NUL-terminated paths of the nodes directly below path, or
+/// with SERVICE_LIST_RECURSIVE of every service registered below it. Return the
+/// length of the whole list, which may exceed buf, or ENOENT for an unknown path.
+pub fn service_list(path: &str, buf: &mut [u8], flags: usize) -> isize {
+    sys_service_list(path, buf, flags)
+}
+
+/// Return the pid of the task registered as path, or ENOENT.
+pub fn service_lookup(path: &str) -> isize {
+    sys_service_lookup(path)
+}
+
+/// Block until a task is registered as path and return its pid, or ETIMEDOUT.
+pub fn service_wait(path: &str, timeout_ms: usize) -> isize {
+    sys_service_wait(path, timeout_ms)
+}
+
+/// Bind the calling task to path, clients connecting to it write to its mailbox.
+/// Return EEXIST if another live task or a topic holds the name.
+pub fn service_publish(path: &str) -> isize {
+    sys_service_publish(path, usize::MAX)
+}
+
+/// Like service_publish, but clients get a copy of the channel handle, which
+/// must carry RIGHT_WRITE and RIGHT_DUPLICATE.
+pub fn service_publish_handle(path: &str, handle: usize) -> isize {
+    sys_service_publish(path, handle)
+}
+
+/// Drop a name published by the calling task, EACCES if another task owns it.
+pub fn service_unpublish(path: &str) -> isize {
+    sys_service_unpublish(path)
+}
+
+/// Drop path and every name below it, EACCES if another task serves one of them.
+pub fn service_remove(path: &str) -> isize {
+    sys_service_remove(path)
+}
+
+/// Return 0 if task pid may send to the service at path under the access policy,
+/// EACCES if it may not. Sends it may not make fail with EACCES.
+pub fn access_check(pid: usize, path: &str) -> isize {
+    sys_access_check(pid, path)
+}
+
+pub const DISPATCH_ROUND_ROBIN: usize = 0;
+pub const DISPATCH_LEAST_QUEUED: usize = 1;
+
+/// Serve path together with the other tasks which joined it. Each message written
+/// to path goes to one instance, picked as dispatch of the first instance says.
+/// Return EEXIST if a single task is registered as path.
+pub fn service_join(path: &str, dispatch: usize) -> isize {
+    sys_service_join(path, dispatch)
+}
+
+/// Stop serving path as an instance of its pool.
+pub fn service_leave(path: &str) -> isize {
+    sys_service_leave(path)
+}
+
+pub const WATCHDOG_LOG: usize = 0;
+pub const WATCHDOG_RESTART: usize = 1;
+pub const WATCHDOG_NOTIFY: usize = 2;
+
+/// Ask the kernel to watch the service path this task is registered as. Unless the
+/// task calls heartbeat at least every interval_ms, the kernel logs it, kills and
+/// restarts the service, or sends path to the mailbox of supervisor with the pid of
+/// this task as the sender. An interval of 0 stops watching.
+pub fn service_watchdog(path: &str, interval_ms: usize, action: usize, supervisor: Option<&str>) -> isize {
+    sys_service_watchdog(path, interval_ms, action, supervisor)
+}
+
+/// Restart the watchdog intervals of the services of this task.
+pub fn heartbeat() -> isize {
+    sys_heartbeat()
+}
+
+pub const RESTART_NEVER: usize = 0;
+pub const RESTART_ON_FAILURE: usize = 1;
+pub const RESTART_ALWAYS: usize = 2;
+
+/// Restart a service up to max_retries times once its task exits, waiting
+/// backoff_ms before the first restart and twice as long before each further one.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RestartPolicy {
+    pub mode: usize,
+    pub max_retries: usize,
+    pub backoff_ms: usize,
 }
 
 pub fn register(file: &str, service: &str) -> isize {
-    sys_register(file, service)
-}
\ No newline at end of file
+    sys_register(file, service, None)
+}
+
+/// Like register, but start file again according to restart whenever it exits.
+pub fn register_with_restart(file: &str, service: &str, restart: &RestartPolicy) -> isize {
+    sys_register(file, service, Some(restart))
+}
+
+/// Jobs and timing failures of a periodic task.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PeriodicStats {
+    pub jobs: usize,
+    pub deadline_misses: usize,
+    pub budget_overruns: usize,
+}
+
+/// Make the caller periodic: a job is released every period_ms, the first one
+/// right away, and should run for at most budget_ms and complete within
+/// deadline_ms of its release (0 for the whole period). Periodic tasks run
+/// before all others, by earliest deadline first, or by shortest period in a
+/// kernel built with sched-rm. A period of 0 turns it off.
+pub fn set_periodic(period_ms: usize, budget_ms: usize, deadline_ms: usize) -> isize {
+    sys_set_periodic(period_ms, budget_ms, deadline_ms)
+}
+
+/// Complete the current job and block until the next one is released.
+pub fn wait_period() -> isize {
+    sys_wait_period()
+}
+
+/// Counters of the periodic task pid, 0 for the caller.
+pub fn periodic_stats(pid: usize, stats: &mut PeriodicStats) -> isize {
+    sys_periodic_stats(pid, stats as *mut _)
+}
+
+/// Store the name of the time partition of task pid, 0 for the caller, in buf
+/// and return its length, ENOENT if it runs in no partition.
+pub fn task_partition(pid: usize, buf: &mut [u8]) -> isize {
+    sys_task_partition(pid, buf)
+}
@@ -0,0 +1,82 @@
+//! Client side of the user-space name server, which the kernel starts when
+//! built with the user-nameserver feature. Requests are calls on
+//! BOOTSTRAP_HANDLE: one opcode byte followed by the name, answered with the
+//! status as a little-endian isize and, for a lookup, the handle of the service.
+//! A lookup answers with the pid serving the name as its status. With the name
+//! server running, the kernel serves register, service_lookup, service_connect
+//! and service_wait through it as well.
+
+use super::{channel_call, channel_call_handles, errno::EINVAL, MessageInfo, TIMEOUT_INFINITE};
+
+/// Channel to the name server, inherited by every task forked from initproc.
+pub const BOOTSTRAP_HANDLE: usize = 1;
+
+pub const NS_REGISTER: u8 = 1;
+pub const NS_LOOKUP: u8 = 2;
+pub const NS_UNREGISTER: u8 = 3;
+
+/// Longest name the name server accepts.
+pub const NS_MAX_NAME: usize = 64;
+
+pub const NS_REQUEST_SIZE: usize = NS_MAX_NAME + 1;
+
+// Encode a request into buf, return its length. Trailing NULs of name are
+// ignored so that the paths used with the kernel registry work as they are.
+fn encode(op: u8, name: &str, buf: &mut [u8; NS_REQUEST_SIZE]) -> Option<usize> {
+    let name = name.trim_end_matches('\0').as_bytes();
+    if name.is_empty() || name.len() > NS_MAX_NAME {
+        return None;
+    }
+    buf[0] = op;
+    buf[1..=name.len()].copy_from_slice(name);
+    Some(name.len() + 1)
+}
+
+fn call(op: u8, name: &str, handles: &[usize], reply_handles: &mut [usize]) -> isize {
+    let mut request = [0u8; NS_REQUEST_SIZE];
+    let len = match encode(op, name, &mut request) {
+        Some(len) => len,
+        None => return EINVAL,
+    };
+    let mut reply = [0u8; 8];
+    let mut info = MessageInfo::default();
+    let result = if handles.is_empty() && reply_handles.is_empty() {
+        channel_call(BOOTSTRAP_HANDLE, &request[..len], &mut reply, TIMEOUT_INFINITE, &mut info)
+    } else {
+        channel_call_handles(
+            BOOTSTRAP_HANDLE,
+            &request[..len],
+            handles,
+            &mut reply,
+            reply_handles,
+            TIMEOUT_INFINITE,
+            &mut info,
+        )
+    };
+    if result < 0 {
+        return result;
+    }
+    isize::from_le_bytes(reply)
+}
+
+/// Bind name to handle, which moves to the name server. Clients looking the
+/// name up get a copy of it, through which sends obey the access policy of
+/// name. Return EEXIST if another live task holds the name.
+pub fn register(name: &str, handle: usize) -> isize {
+    call(NS_REGISTER, name, &[handle], &mut [])
+}
+
+/// Return a handle to the service registered as name, or ENOENT.
+pub fn lookup(name: &str) -> isize {
+    let mut handles = [0usize; 1];
+    let status = call(NS_LOOKUP, name, &[], &mut handles);
+    if status < 0 {
+        return status;
+    }
+    handles[0] as isize
+}
+
+/// Drop a name registered by this task, EACCES if another task holds it.
+pub fn unregister(name: &str) -> isize {
+    call(NS_UNREGISTER, name, &[], &mut [])
+}
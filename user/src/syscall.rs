@@ -1,9 +1,14 @@
+use super::{ChannelLimits, ChannelStats, MessageInfo, PeriodicStats, RestartPolicy, WaitItem};
+
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_GET_PRIORITY: usize = 141;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_FORK: usize = 220;
@@ -16,6 +21,39 @@ const SYSCALL_MMAP_CREATE: usize = 401;
 const SYSCALL_SERVICE_REGISTER: usize = 500;
 const SYSCALL_CHANNEL_READ: usize = 501;
 const SYSCALL_CHANNEL_WRITE: usize = 502;
+const SYSCALL_CHANNEL_CREATE: usize = 503;
+const SYSCALL_SERVICE_CONNECT: usize = 504;
+const SYSCALL_HANDLE_DUPLICATE: usize = 505;
+const SYSCALL_HANDLE_CLOSE: usize = 506;
+const SYSCALL_CHANNEL_CALL: usize = 507;
+const SYSCALL_CHANNEL_REPLY: usize = 508;
+const SYSCALL_OBJECT_WAIT_ONE: usize = 509;
+const SYSCALL_CHANNEL_STATS: usize = 510;
+const SYSCALL_TIMER_CREATE: usize = 511;
+const SYSCALL_TIMER_SET: usize = 512;
+const SYSCALL_TIMER_CANCEL: usize = 513;
+const SYSCALL_OBJECT_WAIT_MANY: usize = 514;
+const SYSCALL_CHANNEL_GRANT: usize = 515;
+const SYSCALL_TOPIC_OPEN: usize = 516;
+const SYSCALL_TOPIC_SUBSCRIBE: usize = 517;
+const SYSCALL_TOPIC_PUBLISH: usize = 518;
+const SYSCALL_SERVICE_LIST: usize = 519;
+const SYSCALL_SERVICE_LOOKUP: usize = 520;
+const SYSCALL_SERVICE_WAIT: usize = 521;
+const SYSCALL_SERVICE_PUBLISH: usize = 522;
+const SYSCALL_SERVICE_UNPUBLISH: usize = 523;
+const SYSCALL_ACCESS_CHECK: usize = 524;
+const SYSCALL_SERVICE_WATCHDOG: usize = 525;
+const SYSCALL_HEARTBEAT: usize = 526;
+const SYSCALL_SERVICE_JOIN: usize = 527;
+const SYSCALL_SERVICE_LEAVE: usize = 528;
+const SYSCALL_TASK_SET_PERIODIC: usize = 529;
+const SYSCALL_TASK_WAIT_PERIOD: usize = 530;
+const SYSCALL_TASK_PERIODIC_STATS: usize = 531;
+const SYSCALL_SLEEP_UNTIL: usize = 532;
+const SYSCALL_SERVICE_REMOVE: usize = 533;
+const SYSCALL_CHANNEL_TAKE_REPLY: usize = 534;
+const SYSCALL_TASK_PARTITION: usize = 535;
 
 fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut ret: isize;
@@ -30,6 +68,20 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (ret)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]),
+              "{x13}" (args[3]), "{x14}" (args[4]), "{x15}" (args[5]), "{x17}" (id)
+            : "memory"
+            : "volatile"
+        );
+    }
+    ret
+}
+
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0])
 }
@@ -55,6 +107,14 @@ pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
+pub fn sys_set_priority(pid: usize, priority: usize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, [pid, priority, 0])
+}
+
+pub fn sys_get_priority(pid: usize) -> isize {
+    syscall(SYSCALL_GET_PRIORITY, [pid, 0, 0])
+}
+
 pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
@@ -71,8 +131,16 @@ pub fn sys_exec(path: &str) -> isize {
     syscall(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: usize) -> isize {
+    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options])
+}
+
+pub fn sys_nanosleep(duration_ns: usize) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [duration_ns, 0, 0])
+}
+
+pub fn sys_sleep_until(deadline_ms: usize) -> isize {
+    syscall(SYSCALL_SLEEP_UNTIL, [deadline_ms, 0, 0])
 }
 
 pub fn sys_create_task(path: &str) -> isize {
@@ -91,14 +159,222 @@ pub fn sys_munmap(start: usize, len: usize) -> isize {
     syscall(SYSCALL_MUNMAP, [start, len, 0])
 }
 
-pub fn sys_channel_read(buf: &mut [u8], len: usize) -> isize {
-    syscall(SYSCALL_CHANNEL_READ, [buf.as_mut_ptr() as usize, len, 0])
+#[repr(C)]
+struct MessageBuffer {
+    bytes: *mut u8,
+    num_bytes: usize,
+    handles: *mut usize,
+    num_handles: usize,
 }
 
-pub fn sys_channel_write(path: &str, buf: &[u8], len: usize) -> isize {
-    syscall(SYSCALL_CHANNEL_WRITE, [path.as_ptr() as usize, buf.as_ptr() as usize, len])
+pub fn sys_channel_read(
+    handle: usize,
+    buf: &mut [u8],
+    handles: &mut [usize],
+    flags: usize,
+    info: *mut MessageInfo,
+) -> isize {
+    let buffer = MessageBuffer {
+        bytes: buf.as_mut_ptr(),
+        num_bytes: buf.len(),
+        handles: handles.as_mut_ptr(),
+        num_handles: handles.len(),
+    };
+    syscall6(SYSCALL_CHANNEL_READ, [handle, &buffer as *const _ as usize, flags, info as usize, 0, 0])
 }
 
-pub fn sys_register(file: &str, service: &str) -> isize {
-    syscall(SYSCALL_SERVICE_REGISTER, [file.as_ptr() as usize, service.as_ptr() as usize, 0])
-}
\ No newline at end of file
+pub fn sys_channel_write(handle: usize, buf: &[u8], handles: &[usize], flags: usize) -> isize {
+    syscall6(
+        SYSCALL_CHANNEL_WRITE,
+        [handle, buf.as_ptr() as usize, buf.len(), handles.as_ptr() as usize, handles.len(), flags],
+    )
+}
+
+pub fn sys_channel_create(handles: &mut [usize; 2], limits: *const ChannelLimits) -> isize {
+    syscall(SYSCALL_CHANNEL_CREATE, [handles.as_mut_ptr() as usize, limits as usize, 0])
+}
+
+pub fn sys_service_connect(path: &str) -> isize {
+    syscall(SYSCALL_SERVICE_CONNECT, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_handle_duplicate(handle: usize, rights: usize) -> isize {
+    syscall(SYSCALL_HANDLE_DUPLICATE, [handle, rights, 0])
+}
+
+pub fn sys_handle_close(handle: usize) -> isize {
+    syscall(SYSCALL_HANDLE_CLOSE, [handle, 0, 0])
+}
+
+#[repr(C)]
+struct CallArgs {
+    wr_bytes: *const u8,
+    wr_num_bytes: usize,
+    wr_handles: *const usize,
+    wr_num_handles: usize,
+    rd: MessageBuffer,
+    timeout_ms: usize,
+}
+
+pub fn sys_channel_call(
+    handle: usize,
+    request: &[u8],
+    request_handles: &[usize],
+    reply: &mut [u8],
+    reply_handles: &mut [usize],
+    timeout_ms: usize,
+    info: *mut MessageInfo,
+) -> isize {
+    let args = CallArgs {
+        wr_bytes: request.as_ptr(),
+        wr_num_bytes: request.len(),
+        wr_handles: request_handles.as_ptr(),
+        wr_num_handles: request_handles.len(),
+        rd: MessageBuffer {
+            bytes: reply.as_mut_ptr(),
+            num_bytes: reply.len(),
+            handles: reply_handles.as_mut_ptr(),
+            num_handles: reply_handles.len(),
+        },
+        timeout_ms,
+    };
+    syscall(SYSCALL_CHANNEL_CALL, [handle, &args as *const _ as usize, info as usize])
+}
+
+pub fn sys_channel_take_reply(
+    handle: usize,
+    txid: usize,
+    buf: &mut [u8],
+    handles: &mut [usize],
+    info: *mut MessageInfo,
+) -> isize {
+    let buffer = MessageBuffer {
+        bytes: buf.as_mut_ptr(),
+        num_bytes: buf.len(),
+        handles: handles.as_mut_ptr(),
+        num_handles: handles.len(),
+    };
+    syscall6(SYSCALL_CHANNEL_TAKE_REPLY, [handle, txid, &buffer as *const _ as usize, info as usize, 0, 0])
+}
+
+pub fn sys_channel_reply(handle: usize, txid: usize, buf: &[u8], handles: &[usize]) -> isize {
+    syscall6(
+        SYSCALL_CHANNEL_REPLY,
+        [handle, txid, buf.as_ptr() as usize, buf.len(), handles.as_ptr() as usize, handles.len()],
+    )
+}
+
+pub fn sys_register(file: &str, service: &str, restart: Option<&RestartPolicy>) -> isize {
+    let restart = restart.map_or(core::ptr::null(), |restart| restart as *const RestartPolicy);
+    syscall(SYSCALL_SERVICE_REGISTER, [file.as_ptr() as usize, service.as_ptr() as usize, restart as usize])
+}
+
+pub fn sys_object_wait_one(handle: usize, signals: usize, timeout_ms: usize, observed: *mut u32) -> isize {
+    syscall6(SYSCALL_OBJECT_WAIT_ONE, [handle, signals, timeout_ms, observed as usize, 0, 0])
+}
+
+pub fn sys_channel_stats(handle: usize, stats: *mut ChannelStats) -> isize {
+    syscall(SYSCALL_CHANNEL_STATS, [handle, stats as usize, 0])
+}
+
+pub fn sys_timer_create() -> isize {
+    syscall(SYSCALL_TIMER_CREATE, [0, 0, 0])
+}
+
+pub fn sys_timer_set(handle: usize, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_TIMER_SET, [handle, timeout_ms, 0])
+}
+
+pub fn sys_timer_cancel(handle: usize) -> isize {
+    syscall(SYSCALL_TIMER_CANCEL, [handle, 0, 0])
+}
+
+pub fn sys_object_wait_many(items: &mut [WaitItem], timeout_ms: usize) -> isize {
+    syscall(SYSCALL_OBJECT_WAIT_MANY, [items.as_mut_ptr() as usize, items.len(), timeout_ms])
+}
+
+pub fn sys_channel_grant(handle: usize, buf: &[u8], grant_addr: usize, grant_len: usize, flags: usize) -> isize {
+    syscall6(
+        SYSCALL_CHANNEL_GRANT,
+        [handle, buf.as_ptr() as usize, buf.len(), grant_addr, grant_len, flags],
+    )
+}
+
+pub fn sys_topic_open(path: &str) -> isize {
+    syscall(SYSCALL_TOPIC_OPEN, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_topic_subscribe(path: &str, policy: usize, limits: *const ChannelLimits) -> isize {
+    syscall(SYSCALL_TOPIC_SUBSCRIBE, [path.as_ptr() as usize, policy, limits as usize])
+}
+
+pub fn sys_topic_publish(handle: usize, buf: &[u8]) -> isize {
+    syscall(SYSCALL_TOPIC_PUBLISH, [handle, buf.as_ptr() as usize, buf.len()])
+}
+
+pub fn sys_service_list(path: &str, buf: &mut [u8], flags: usize) -> isize {
+    syscall6(
+        SYSCALL_SERVICE_LIST,
+        [path.as_ptr() as usize, buf.as_mut_ptr() as usize, buf.len(), flags, 0, 0],
+    )
+}
+
+pub fn sys_service_lookup(path: &str) -> isize {
+    syscall(SYSCALL_SERVICE_LOOKUP, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_service_wait(path: &str, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_SERVICE_WAIT, [path.as_ptr() as usize, timeout_ms, 0])
+}
+
+pub fn sys_service_publish(path: &str, handle: usize) -> isize {
+    syscall(SYSCALL_SERVICE_PUBLISH, [path.as_ptr() as usize, handle, 0])
+}
+
+pub fn sys_service_unpublish(path: &str) -> isize {
+    syscall(SYSCALL_SERVICE_UNPUBLISH, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_service_remove(path: &str) -> isize {
+    syscall(SYSCALL_SERVICE_REMOVE, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_access_check(pid: usize, path: &str) -> isize {
+    syscall(SYSCALL_ACCESS_CHECK, [pid, path.as_ptr() as usize, 0])
+}
+
+pub fn sys_service_watchdog(path: &str, interval_ms: usize, action: usize, supervisor: Option<&str>) -> isize {
+    let supervisor = supervisor.map_or(core::ptr::null(), |supervisor| supervisor.as_ptr());
+    syscall6(
+        SYSCALL_SERVICE_WATCHDOG,
+        [path.as_ptr() as usize, interval_ms, action, supervisor as usize, 0, 0],
+    )
+}
+
+pub fn sys_heartbeat() -> isize {
+    syscall(SYSCALL_HEARTBEAT, [0, 0, 0])
+}
+
+pub fn sys_service_join(path: &str, dispatch: usize) -> isize {
+    syscall(SYSCALL_SERVICE_JOIN, [path.as_ptr() as usize, dispatch, 0])
+}
+
+pub fn sys_service_leave(path: &str) -> isize {
+    syscall(SYSCALL_SERVICE_LEAVE, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_set_periodic(period_ms: usize, budget_ms: usize, deadline_ms: usize) -> isize {
+    syscall(SYSCALL_TASK_SET_PERIODIC, [period_ms, budget_ms, deadline_ms])
+}
+
+pub fn sys_wait_period() -> isize {
+    syscall(SYSCALL_TASK_WAIT_PERIOD, [0, 0, 0])
+}
+
+pub fn sys_periodic_stats(pid: usize, stats: *mut PeriodicStats) -> isize {
+    syscall(SYSCALL_TASK_PERIODIC_STATS, [pid, stats as usize, 0])
+}
+
+pub fn sys_task_partition(pid: usize, buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_TASK_PARTITION, [pid, buf.as_mut_ptr() as usize, buf.len()])
+}